@@ -0,0 +1,271 @@
+//! Snapshots of the accessibility tree.
+//!
+//! [`Element::accessibility_snapshot`] describes an element and its descendants the way
+//! assistive technologies see them: as a tree of [`AccessibilityNode`]s, each with a role, an
+//! accessible name and a set of states. Elements without a meaningful role, such as most `<div>`s
+//! and `<span>`s, are left out, and their children take their place. Since the snapshot can be
+//! serialized, it can be compared against one stored from an earlier run:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let c = fantoccini::ClientBuilder::native().connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = fantoccini::ClientBuilder::rustls().expect("rustls initialization").connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! use fantoccini::Locator;
+//!
+//! c.goto("https://www.wikipedia.org/").await?;
+//! let form = c.find(Locator::Css("#search-form")).await?;
+//! let snapshot = form.accessibility_snapshot().await?;
+//! let expected = std::fs::read_to_string("tests/snapshots/search-form.json")?;
+//! assert_eq!(snapshot, serde_json::from_str(&expected)?);
+//! # c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Roles and names come from [`Element::computed_role`] and [`Element::computed_label`]. With
+//! drivers that do not support those, they are approximated in the page instead.
+use crate::elements::Element;
+use crate::error;
+use crate::wd::ARIA_FUNCTIONS;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+/// Roles of elements that are left out of snapshots.
+const IGNORED_ROLES: &[&str] = &["", "generic", "none", "presentation"];
+
+/// Collect the visible elements below and including `arguments[0]`, along with their states and
+/// an approximation of their roles and names.
+const TREE: &str = r#"
+const stateOf = (el) => {
+    const aria = (name) => el.getAttribute('aria-' + name);
+    const states = [];
+    if (el.disabled || aria('disabled') === 'true') states.push('disabled');
+    if (el.checked || aria('checked') === 'true') states.push('checked');
+    if (el.indeterminate || aria('checked') === 'mixed') states.push('mixed');
+    if (el.selected || aria('selected') === 'true') states.push('selected');
+    if (aria('expanded') === 'true') states.push('expanded');
+    if (aria('expanded') === 'false') states.push('collapsed');
+    if (aria('pressed') === 'true') states.push('pressed');
+    if (el.required || aria('required') === 'true') states.push('required');
+    if (el.readOnly || aria('readonly') === 'true') states.push('readonly');
+    if (aria('invalid') && aria('invalid') !== 'false') states.push('invalid');
+    if (el.getRootNode().activeElement === el) states.push('focused');
+    return states;
+};
+const hidden = (el) => {
+    if (el.hidden || el.getAttribute('aria-hidden') === 'true') return true;
+    const style = getComputedStyle(el);
+    return style.display === 'none' || style.visibility === 'hidden';
+};
+const walk = (el) => {
+    const children = Array.from(el.shadowRoot ? el.shadowRoot.children : [])
+        .concat(Array.from(el.children))
+        .filter((child) => !hidden(child))
+        .map(walk);
+    const r = role(el);
+    return [el, r, r ? name(el) : '', stateOf(el), children];
+};
+return walk(arguments[0]);
+"#;
+
+/// An element in a snapshot of the accessibility tree.
+///
+/// See the [module-level documentation](self) for how to take one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessibilityNode {
+    /// The element's [ARIA role](https://www.w3.org/TR/wai-aria/#role_definitions), such as
+    /// `"button"`.
+    pub role: String,
+    /// The element's [accessible name](https://www.w3.org/TR/accname/), with runs of whitespace
+    /// collapsed.
+    pub name: String,
+    /// The states the element is in, in this order: `"disabled"`, `"checked"`, `"mixed"`,
+    /// `"selected"`, `"expanded"`, `"collapsed"`, `"pressed"`, `"required"`, `"readonly"`,
+    /// `"invalid"` and `"focused"`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<String>,
+    /// The nodes for the element's descendants, in document order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<AccessibilityNode>,
+}
+
+/// Whether `e` means that the driver does not implement a command.
+fn is_unsupported(e: &error::CmdError) -> bool {
+    matches!(
+        e,
+        error::CmdError::Standard(w) if matches!(
+            w.error,
+            error::ErrorStatus::UnknownCommand
+                | error::ErrorStatus::UnknownMethod
+                | error::ErrorStatus::UnsupportedOperation
+        )
+    )
+}
+
+/// Collapse runs of whitespace in `s`, and trim it.
+pub(crate) fn normalize_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Gets computed roles and labels, and remembers when the driver does not support them.
+#[derive(Debug)]
+pub(crate) struct Computed {
+    role: bool,
+    label: bool,
+}
+
+impl Default for Computed {
+    fn default() -> Self {
+        Computed {
+            role: true,
+            label: true,
+        }
+    }
+}
+
+impl Computed {
+    /// The computed role of `element`, or `None` if the driver does not support computed roles.
+    pub(crate) async fn role(
+        &mut self,
+        element: &Element,
+    ) -> Result<Option<String>, error::CmdError> {
+        if !self.role {
+            return Ok(None);
+        }
+        match element.computed_role().await {
+            Ok(role) => Ok(Some(role)),
+            Err(e) if is_unsupported(&e) => {
+                self.role = false;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The computed label of `element`, or `None` if the driver does not support computed
+    /// labels.
+    pub(crate) async fn label(
+        &mut self,
+        element: &Element,
+    ) -> Result<Option<String>, error::CmdError> {
+        if !self.label {
+            return Ok(None);
+        }
+        match element.computed_label().await {
+            Ok(label) => Ok(Some(normalize_whitespace(&label))),
+            Err(e) if is_unsupported(&e) => {
+                self.label = false;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// An element collected by [`TREE`], before its role and name are settled.
+struct RawNode {
+    element: Element,
+    role: String,
+    name: String,
+    states: Vec<String>,
+    children: Vec<usize>,
+}
+
+/// Flatten the tree returned by [`TREE`] into `nodes` in pre-order, and return the index of its
+/// root.
+fn flatten(
+    element: &Element,
+    tree: Json,
+    nodes: &mut Vec<RawNode>,
+) -> Result<usize, error::CmdError> {
+    type Fields = (Json, String, String, Vec<String>, Vec<Json>);
+    let (found, role, name, states, children): Fields =
+        serde_json::from_value(tree.clone()).map_err(|_| error::CmdError::NotW3C(tree))?;
+    let index = nodes.len();
+    nodes.push(RawNode {
+        element: Element {
+            client: element.client.clone(),
+            element: element.client.parse_lookup(found)?,
+        },
+        role,
+        name: normalize_whitespace(&name),
+        states,
+        children: Vec::new(),
+    });
+    for child in children {
+        let child = flatten(element, child, nodes)?;
+        nodes[index].children.push(child);
+    }
+    Ok(index)
+}
+
+/// Build the node at `index`, or the nodes of its children if it is to be left out.
+fn build(nodes: &mut [Option<RawNode>], index: usize, out: &mut Vec<AccessibilityNode>) {
+    let node = nodes[index].take().expect("every node has one parent");
+    let mut children = Vec::new();
+    for child in node.children {
+        build(nodes, child, &mut children);
+    }
+    if IGNORED_ROLES.contains(&&*node.role) {
+        out.extend(children);
+    } else {
+        out.push(AccessibilityNode {
+            role: node.role,
+            name: node.name,
+            states: node.states,
+            children,
+        });
+    }
+}
+
+/// [Accessibility](crate::accessibility)
+impl Element {
+    /// Take a snapshot of the accessibility tree of this element and its visible descendants,
+    /// including those in open shadow roots.
+    ///
+    /// The returned node is for this element, even if it has no meaningful role. See the
+    /// [module-level documentation](crate::accessibility) for what the snapshot contains.
+    pub async fn accessibility_snapshot(&self) -> Result<AccessibilityNode, error::CmdError> {
+        let script = [
+            "const normalize = (s) => (s || '').replace(/\\s+/g, ' ').trim();\n",
+            ARIA_FUNCTIONS,
+            TREE,
+        ]
+        .concat();
+        let tree = self.client.execute(&script, vec![via_json!(self)]).await?;
+        let mut nodes = Vec::new();
+        flatten(self, tree, &mut nodes)?;
+
+        let mut computed = Computed::default();
+        for node in &mut nodes {
+            if let Some(role) = computed.role(&node.element).await? {
+                node.role = role;
+            }
+            if IGNORED_ROLES.contains(&&*node.role) {
+                // the name of an element that is left out does not matter
+                continue;
+            }
+            if let Some(label) = computed.label(&node.element).await? {
+                node.name = label;
+            }
+        }
+
+        let mut nodes: Vec<_> = nodes.into_iter().map(Some).collect();
+        let root = nodes[0].take().expect("the root is the first node");
+        let mut children = Vec::new();
+        for child in root.children {
+            build(&mut nodes, child, &mut children);
+        }
+        Ok(AccessibilityNode {
+            role: root.role,
+            name: root.name,
+            states: root.states,
+            children,
+        })
+    }
+}
@@ -0,0 +1,543 @@
+//! [WebDriver BiDi](https://w3c.github.io/webdriver-bidi/) support.
+//!
+//! Classic WebDriver is strictly request/response, which means that there is no way for the
+//! browser to tell us about things that happen on the page (such as console messages or
+//! navigations) as they occur. WebDriver BiDi adds a bidirectional WebSocket transport next to the
+//! HTTP session over which commands can be sent and events can be received.
+//!
+//! To use it, ask for the `webSocketUrl` capability when creating the session. If the remote end
+//! supports BiDi, it will reply with the URL of the WebSocket, and the resulting [`Client`] will
+//! be connected to it automatically:
+//!
+//! ```no_run
+//! # use fantoccini::ClientBuilder;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::bidi::{self, BiDiEvent};
+//!
+//! let mut caps = serde_json::map::Map::new();
+//! caps.insert("webSocketUrl".to_string(), serde_json::Value::Bool(true));
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let c = ClientBuilder::native().capabilities(caps).connect("http://localhost:4444").await?;
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = ClientBuilder::rustls()?.capabilities(caps).connect("http://localhost:4444").await?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//!
+//! let bidi = c.bidi().expect("remote end does not support WebDriver BiDi");
+//! let mut events = bidi.events();
+//! bidi.subscribe(&[bidi::LogEntryAdded::METHOD]).await?;
+//!
+//! c.execute("console.log('hello')", vec![]).await?;
+//! while let Some(event) = events.next().await {
+//!     if let Some(entry) = event.parse::<bidi::LogEntryAdded>() {
+//!         println!("console: {:?}", entry?.text);
+//!         break;
+//!     }
+//! }
+//!
+//! c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The connection shares the lifetime of the HTTP session: it is closed by [`Client::close`], or
+//! once the last handle to the session has been dropped.
+//!
+//! Secure `wss://` WebSocket URLs, as handed out by some cloud providers, are connected to using
+//! the TLS implementation selected through the `native-tls` or `rustls-tls` feature. If both are
+//! enabled, `native-tls` is used. Without either, only plain `ws://` URLs can be connected to.
+
+use crate::error;
+#[cfg(doc)]
+use crate::Client;
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+
+/// The number of events that are buffered for each [`Events`] receiver before older ones are
+/// dropped.
+const EVENT_BUFFER: usize = 1024;
+
+type Ack = oneshot::Sender<Result<Json, error::CmdError>>;
+
+#[derive(Debug)]
+enum Task {
+    Command {
+        method: String,
+        params: Json,
+        ack: Ack,
+    },
+    Close {
+        ack: oneshot::Sender<()>,
+    },
+}
+
+/// A BiDi command with a typed response.
+///
+/// Anything that implements this trait can be sent using [`BiDi::send`]. The implementing type
+/// is serialized as the command's `params`.
+pub trait BiDiCommand: Serialize {
+    /// The name of the command, such as `"session.subscribe"`.
+    const METHOD: &'static str;
+
+    /// The type the command's `result` deserializes into.
+    type Output: DeserializeOwned;
+}
+
+/// A BiDi event with a typed payload.
+///
+/// See [`Event::parse`].
+pub trait BiDiEvent: DeserializeOwned {
+    /// The name of the event, such as `"log.entryAdded"`.
+    const METHOD: &'static str;
+}
+
+/// An event emitted by the remote end.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Event {
+    /// The name of the event, such as `"browsingContext.load"`.
+    pub method: String,
+    /// The event payload.
+    pub params: Json,
+}
+
+impl Event {
+    /// Parse this event as the given typed event.
+    ///
+    /// Returns `None` if this event is not an `E`.
+    pub fn parse<E: BiDiEvent>(&self) -> Option<Result<E, serde_json::Error>> {
+        if self.method != E::METHOD {
+            return None;
+        }
+        Some(serde_json::from_value(self.params.clone()))
+    }
+}
+
+/// A receiver of [`Event`]s, obtained through [`BiDi::events`].
+///
+/// Only events that arrive after the receiver was created are observed. Note that the remote end
+/// only emits events that have been [subscribed](BiDi::subscribe) to.
+#[derive(Debug)]
+pub struct Events {
+    rx: broadcast::Receiver<Event>,
+}
+
+impl Events {
+    /// Wait for the next event.
+    ///
+    /// Returns `None` once the connection has been closed. If this receiver falls too far behind,
+    /// the oldest events are skipped.
+    pub async fn next(&mut self) -> Option<Event> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// A handle to the WebDriver BiDi connection of a session.
+///
+/// Obtained through [`Client::bidi`].
+#[derive(Clone, Debug)]
+pub struct BiDi {
+    tx: mpsc::UnboundedSender<Task>,
+    // the connection task clears this when it exits, so that receivers observe the close
+    events: Arc<Mutex<Option<broadcast::Sender<Event>>>>,
+}
+
+impl BiDi {
+    pub(crate) async fn connect(url: &str) -> Result<Self, error::NewSessionError> {
+        let (ws, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .map_err(|e| error::NewSessionError::Lost(io::Error::new(io::ErrorKind::Other, e)))?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let events = Arc::new(Mutex::new(Some(events)));
+        tokio::spawn(run(ws, rx, Arc::clone(&events)));
+        Ok(BiDi { tx, events })
+    }
+
+    /// Send the given typed command, and wait for its result.
+    pub async fn send<C: BiDiCommand>(&self, cmd: C) -> Result<C::Output, error::CmdError> {
+        let params = serde_json::to_value(cmd)?;
+        let res = self.send_raw(C::METHOD, params).await?;
+        Ok(serde_json::from_value(res)?)
+    }
+
+    /// Send a command with the given `method` name and `params`, and wait for its result.
+    pub async fn send_raw(&self, method: &str, params: Json) -> Result<Json, error::CmdError> {
+        let (ack, rx) = oneshot::channel();
+        self.tx
+            .send(Task::Command {
+                method: method.to_string(),
+                params,
+                ack,
+            })
+            .map_err(|_| closed())?;
+        rx.await.unwrap_or_else(|_| Err(closed()))
+    }
+
+    /// Start receiving events emitted by the remote end.
+    pub fn events(&self) -> Events {
+        let rx = match *self.events.lock().unwrap() {
+            Some(ref events) => events.subscribe(),
+            // the connection is already gone, so hand out a receiver that is closed too
+            None => broadcast::channel(1).1,
+        };
+        Events { rx }
+    }
+
+    /// Ask the remote end to emit the given events (or modules of events) for all browsing
+    /// contexts.
+    ///
+    /// See [session.subscribe](https://w3c.github.io/webdriver-bidi/#command-session-subscribe).
+    pub async fn subscribe(&self, events: &[&str]) -> Result<(), error::CmdError> {
+        self.send(Subscribe {
+            events: events.iter().map(|e| e.to_string()).collect(),
+            contexts: None,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Ask the remote end to no longer emit the given events.
+    ///
+    /// See [session.unsubscribe](https://w3c.github.io/webdriver-bidi/#command-session-unsubscribe).
+    pub async fn unsubscribe(&self, events: &[&str]) -> Result<(), error::CmdError> {
+        self.send(Unsubscribe {
+            events: events.iter().map(|e| e.to_string()).collect(),
+            contexts: None,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Close the WebSocket connection.
+    ///
+    /// Any commands still waiting for a response will fail. This is called for you by
+    /// [`Client::close`].
+    pub async fn close(&self) {
+        let (ack, rx) = oneshot::channel();
+        if self.tx.send(Task::Close { ack }).is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+fn closed() -> error::CmdError {
+    error::CmdError::Lost(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "WebDriver BiDi connection has been closed",
+    ))
+}
+
+/// Turn an `{"type": "error"}` message into the matching error.
+fn parse_error(mut msg: serde_json::Map<String, Json>) -> error::CmdError {
+    let es = match msg.get("error").and_then(Json::as_str) {
+        Some(es) => es.parse(),
+        None => return error::CmdError::NotW3C(Json::Object(msg)),
+    };
+    let es = match es {
+        Ok(es) => es,
+        Err(_) => return error::CmdError::NotW3C(Json::Object(msg)),
+    };
+
+    let message = match msg.remove("message") {
+        Some(Json::String(x)) => x,
+        _ => String::new(),
+    };
+    let mut wd_error = error::WebDriver::new(es, message);
+    if let Some(Json::String(x)) = msg.remove("stacktrace") {
+        wd_error = wd_error.with_stacktrace(x);
+    }
+    error::CmdError::from_webdriver_error(wd_error)
+}
+
+/// The task that owns the WebSocket, multiplexing commands onto it and fanning events out.
+async fn run<S>(
+    ws: tokio_tungstenite::WebSocketStream<S>,
+    mut rx: mpsc::UnboundedReceiver<Task>,
+    shared_events: Arc<Mutex<Option<broadcast::Sender<Event>>>>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let events = match *shared_events.lock().unwrap() {
+        Some(ref events) => events.clone(),
+        None => return,
+    };
+    let (mut sink, mut stream) = ws.split();
+    let mut pending: HashMap<u64, Ack> = HashMap::new();
+    let mut next_id = 0u64;
+
+    loop {
+        tokio::select! {
+            task = rx.recv() => match task {
+                Some(Task::Command { method, params, ack }) => {
+                    next_id += 1;
+                    let msg = serde_json::json!({
+                        "id": next_id,
+                        "method": method,
+                        "params": params,
+                    });
+                    if let Err(e) = sink.send(Message::Text(msg.to_string())).await {
+                        let _ = ack.send(Err(error::CmdError::Lost(io::Error::new(
+                            io::ErrorKind::Other,
+                            e,
+                        ))));
+                        break;
+                    }
+                    pending.insert(next_id, ack);
+                }
+                Some(Task::Close { ack }) => {
+                    let _ = sink.close().await;
+                    let _ = ack.send(());
+                    break;
+                }
+                // all handles are gone, so nobody can observe the connection anymore
+                None => {
+                    let _ = sink.close().await;
+                    break;
+                }
+            },
+            msg = stream.next() => {
+                let msg = match msg {
+                    Some(Ok(Message::Text(msg))) => msg,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+
+                let mut msg = match serde_json::from_str::<Json>(&msg) {
+                    Ok(Json::Object(msg)) => msg,
+                    _ => continue,
+                };
+
+                match msg.get("type").and_then(Json::as_str) {
+                    Some("event") => {
+                        if let Ok(event) = serde_json::from_value(Json::Object(msg)) {
+                            // there may not be anyone listening, which is fine
+                            let _ = events.send(event);
+                        }
+                    }
+                    Some("success") => {
+                        let id = msg.get("id").and_then(Json::as_u64);
+                        if let Some(ack) = id.and_then(|id| pending.remove(&id)) {
+                            let result = msg.remove("result").unwrap_or(Json::Null);
+                            let _ = ack.send(Ok(result));
+                        }
+                    }
+                    Some("error") => {
+                        let id = msg.get("id").and_then(Json::as_u64);
+                        if let Some(ack) = id.and_then(|id| pending.remove(&id)) {
+                            let _ = ack.send(Err(parse_error(msg)));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // fail any commands that are still waiting for a response
+    for (_, ack) in pending {
+        let _ = ack.send(Err(closed()));
+    }
+
+    // and let event receivers know that there won't be any more events
+    shared_events.lock().unwrap().take();
+}
+
+/// Subscribe to events.
+///
+/// See [session.subscribe](https://w3c.github.io/webdriver-bidi/#command-session-subscribe).
+#[derive(Debug, Clone, Serialize)]
+pub struct Subscribe {
+    /// The events, or modules of events, to subscribe to.
+    pub events: Vec<String>,
+    /// The browsing contexts to subscribe in, or all of them if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contexts: Option<Vec<String>>,
+}
+
+impl BiDiCommand for Subscribe {
+    const METHOD: &'static str = "session.subscribe";
+    type Output = Json;
+}
+
+/// Unsubscribe from events.
+///
+/// See [session.unsubscribe](https://w3c.github.io/webdriver-bidi/#command-session-unsubscribe).
+#[derive(Debug, Clone, Serialize)]
+pub struct Unsubscribe {
+    /// The events, or modules of events, to unsubscribe from.
+    pub events: Vec<String>,
+    /// The browsing contexts to unsubscribe in, or all of them if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contexts: Option<Vec<String>>,
+}
+
+impl BiDiCommand for Unsubscribe {
+    const METHOD: &'static str = "session.unsubscribe";
+    type Output = Json;
+}
+
+/// Get the tree of open browsing contexts.
+///
+/// See [browsingContext.getTree](https://w3c.github.io/webdriver-bidi/#command-browsingContext-getTree).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GetTree {
+    /// How many levels of child contexts to include, or all of them if `None`.
+    #[serde(rename = "maxDepth", skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u64>,
+    /// The context to start from, or all top-level contexts if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+}
+
+/// The result of [`GetTree`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContextTree {
+    /// The matching browsing contexts.
+    pub contexts: Vec<ContextInfo>,
+}
+
+/// Information about a single browsing context.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContextInfo {
+    /// The id of the browsing context.
+    pub context: String,
+    /// The URL currently loaded in the browsing context.
+    pub url: String,
+    /// The child contexts, if they were requested.
+    #[serde(default)]
+    pub children: Option<Vec<ContextInfo>>,
+    /// The id of the parent context, if any.
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+impl BiDiCommand for GetTree {
+    const METHOD: &'static str = "browsingContext.getTree";
+    type Output = ContextTree;
+}
+
+/// Navigate a browsing context to the given URL.
+///
+/// See [browsingContext.navigate](https://w3c.github.io/webdriver-bidi/#command-browsingContext-navigate).
+#[derive(Debug, Clone, Serialize)]
+pub struct Navigate {
+    /// The browsing context to navigate.
+    pub context: String,
+    /// The URL to navigate to.
+    pub url: String,
+    /// When to consider the navigation complete: `"none"`, `"interactive"` or `"complete"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait: Option<String>,
+}
+
+impl BiDiCommand for Navigate {
+    const METHOD: &'static str = "browsingContext.navigate";
+    type Output = Json;
+}
+
+/// Evaluate a JavaScript expression in a browsing context.
+///
+/// See [script.evaluate](https://w3c.github.io/webdriver-bidi/#command-script-evaluate).
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluate {
+    /// The expression to evaluate.
+    pub expression: String,
+    /// Where to evaluate the expression, such as `{"context": "<id>"}`.
+    pub target: Json,
+    /// Whether to wait for the result if it is a promise.
+    #[serde(rename = "awaitPromise")]
+    pub await_promise: bool,
+}
+
+impl BiDiCommand for Evaluate {
+    const METHOD: &'static str = "script.evaluate";
+    type Output = Json;
+}
+
+/// A new log entry, such as a console message or a JavaScript error.
+///
+/// See [log.entryAdded](https://w3c.github.io/webdriver-bidi/#event-log-entryAdded).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LogEntryAdded {
+    /// The kind of entry, such as `"console"` or `"javascript"`.
+    #[serde(rename = "type")]
+    pub typ: String,
+    /// The severity of the entry.
+    pub level: String,
+    /// The text of the entry, if any.
+    pub text: Option<String>,
+    /// When the entry was created, in milliseconds since the epoch.
+    pub timestamp: u64,
+}
+
+impl BiDiEvent for LogEntryAdded {
+    const METHOD: &'static str = "log.entryAdded";
+}
+
+/// A page finished loading in a browsing context.
+///
+/// See [browsingContext.load](https://w3c.github.io/webdriver-bidi/#event-browsingContext-load).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContextLoad {
+    /// The browsing context the page was loaded in.
+    pub context: String,
+    /// The URL of the loaded page.
+    pub url: String,
+    /// When the load completed, in milliseconds since the epoch.
+    pub timestamp: u64,
+}
+
+impl BiDiEvent for ContextLoad {
+    const METHOD: &'static str = "browsingContext.load";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_typed_event() {
+        let event = Event {
+            method: "log.entryAdded".to_string(),
+            params: serde_json::json!({
+                "type": "console",
+                "level": "info",
+                "text": "hello",
+                "timestamp": 1,
+                "source": { "realm": "r" },
+            }),
+        };
+        let entry = event.parse::<LogEntryAdded>().unwrap().unwrap();
+        assert_eq!(entry.text.as_deref(), Some("hello"));
+        assert!(event.parse::<ContextLoad>().is_none());
+    }
+
+    #[test]
+    fn parse_error_message() {
+        let msg = serde_json::json!({
+            "type": "error",
+            "id": 1,
+            "error": "no such frame",
+            "message": "gone",
+        });
+        let Json::Object(msg) = msg else {
+            unreachable!()
+        };
+        assert!(parse_error(msg).is_no_such_frame());
+    }
+}
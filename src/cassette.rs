@@ -0,0 +1,479 @@
+//! Record WebDriver sessions to cassettes, and replay them without a browser.
+//!
+//! A [`Recorder`] is a [`Transport`] that passes every request on to the WebDriver server, and
+//! remembers each request along with the response it got. The resulting [`Cassette`] can be
+//! saved to a JSON file, and later served back by a [`Replayer`], which answers requests from the
+//! cassette without talking to any WebDriver server at all. That way, a test suite only needs a
+//! real browser when its cassettes are (re-)recorded.
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::cassette::{Cassette, Recorder, Replayer};
+//! use fantoccini::ClientBuilder;
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let mut builder = ClientBuilder::native();
+//! # #[cfg(feature = "rustls-tls")]
+//! # let mut builder = ClientBuilder::rustls()?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let mut builder = ClientBuilder::new(hyper_util::client::legacy::connect::HttpConnector::new());
+//! // record once, against a real browser
+//! let recorder = Recorder::new();
+//! let c = builder
+//!     .transport(recorder.clone())
+//!     .connect("http://localhost:4444")
+//!     .await?;
+//! c.goto("https://www.rust-lang.org/").await?;
+//! c.close().await?;
+//! recorder.save("tests/cassettes/rust-lang.json")?;
+//!
+//! // replay as often as you like, without one
+//! let cassette = Cassette::load("tests/cassettes/rust-lang.json")?;
+//! let c = builder
+//!     .transport(Replayer::new(cassette))
+//!     .connect("http://localhost:4444")
+//!     .await?;
+//! c.goto("https://www.rust-lang.org/").await?;
+//! assert_eq!(c.current_url().await?.as_str(), "https://www.rust-lang.org/");
+//! c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Session ids, element references, and shadow root references are generally different every
+//! time a cassette is recorded. By default they are therefore ignored when looking for the
+//! recorded interaction that matches a request; see [`MatchRules`] for how to adjust matching.
+
+use crate::error;
+use crate::wd::{Next, Transport, TransportFuture, WireRequest, WireResponse};
+use http::{HeaderMap, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use webdriver::common::{ELEMENT_KEY, SHADOW_KEY};
+
+/// The version of the cassette format written by this version of fantoccini.
+pub const CASSETTE_VERSION: u32 = 1;
+
+/// A recording of the requests a session made, and the responses it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// The version of the cassette format.
+    pub version: u32,
+    /// The recorded interactions, in the order they happened.
+    pub interactions: Vec<Interaction>,
+}
+
+impl Default for Cassette {
+    fn default() -> Self {
+        Cassette {
+            version: CASSETTE_VERSION,
+            interactions: Vec::new(),
+        }
+    }
+}
+
+impl Cassette {
+    /// Read a cassette from the JSON file at `path`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the file is not a cassette, or was written
+    /// in an unsupported version of the cassette format.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let cassette: Cassette = serde_json::from_slice(&fs::read(path)?)?;
+        if cassette.version != CASSETTE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unsupported cassette version {} (expected {})",
+                    cassette.version, CASSETTE_VERSION
+                ),
+            ));
+        }
+        Ok(cassette)
+    }
+
+    /// Write this cassette to a JSON file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// A single request and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The request that was sent.
+    pub request: RecordedRequest,
+    /// The response that was received.
+    pub response: RecordedResponse,
+}
+
+/// A recorded request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The HTTP request method, such as `"POST"`.
+    pub method: String,
+    /// The path of the endpoint, such as `"/session/1234/url"`.
+    pub path: String,
+    /// The JSON request body, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Json>,
+}
+
+/// A recorded response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The `Content-Type` of the response, if any.
+    #[serde(
+        rename = "contentType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub content_type: Option<String>,
+    /// The response body.
+    ///
+    /// JSON responses are stored as JSON, and anything else as a JSON string.
+    pub body: Json,
+}
+
+impl RecordedRequest {
+    fn from_wire(request: &WireRequest) -> Self {
+        RecordedRequest {
+            method: request.method.to_string(),
+            path: request.url.path().to_string(),
+            body: request.body.as_ref().map(|body| {
+                serde_json::from_str(body).unwrap_or_else(|_| Json::String(body.clone()))
+            }),
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn from_wire(response: &WireResponse) -> Self {
+        let content_type = response
+            .headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|ctype| ctype.to_str().ok())
+            .map(String::from);
+        let body = if is_json(content_type.as_deref()) {
+            serde_json::from_str(&response.body)
+                .unwrap_or_else(|_| Json::String(response.body.clone()))
+        } else {
+            Json::String(response.body.clone())
+        };
+        RecordedResponse {
+            status: response.status.as_u16(),
+            content_type,
+            body,
+        }
+    }
+
+    fn to_wire(&self) -> WireResponse {
+        let mut headers = HeaderMap::new();
+        if let Some(ctype) = self
+            .content_type
+            .as_deref()
+            .and_then(|ctype| HeaderValue::from_str(ctype).ok())
+        {
+            headers.insert(http::header::CONTENT_TYPE, ctype);
+        }
+        let body = match self.body {
+            Json::String(ref s) if !is_json(self.content_type.as_deref()) => s.clone(),
+            ref body => body.to_string(),
+        };
+        WireResponse {
+            status: StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            headers,
+            body,
+        }
+    }
+}
+
+fn is_json(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|ctype| ctype.parse::<mime::Mime>().ok())
+        .map(|ctype| {
+            ctype.type_() == mime::APPLICATION_JSON.type_()
+                && ctype.subtype() == mime::APPLICATION_JSON.subtype()
+        })
+        .unwrap_or(false)
+}
+
+/// A [`Transport`] that records every interaction with the WebDriver server into a [`Cassette`].
+///
+/// All clones of a `Recorder` share the same cassette, so keep a clone around to get at the
+/// recording once the session is done.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl Recorder {
+    /// Create a recorder with an empty cassette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a copy of everything that has been recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// Write everything that has been recorded so far to a JSON file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+impl Transport for Recorder {
+    fn send(&self, request: WireRequest, next: Next) -> TransportFuture {
+        let cassette = Arc::clone(&self.cassette);
+        let recorded = RecordedRequest::from_wire(&request);
+        Box::pin(async move {
+            let response = next.run(request).await?;
+            cassette.lock().unwrap().interactions.push(Interaction {
+                request: recorded,
+                response: RecordedResponse::from_wire(&response),
+            });
+            Ok(response)
+        })
+    }
+}
+
+/// Rules for deciding whether a request matches a recorded one.
+///
+/// By default, the HTTP method, the endpoint, and the request body must all match, but session
+/// ids, element references, and shadow root references are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRules {
+    method: bool,
+    endpoint: bool,
+    body: bool,
+    ids: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            method: true,
+            endpoint: true,
+            body: true,
+            ids: false,
+        }
+    }
+}
+
+impl MatchRules {
+    /// Whether the HTTP method (and thus the command) must match.
+    pub fn method(mut self, method: bool) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Whether the endpoint path must match.
+    pub fn endpoint(mut self, endpoint: bool) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Whether the request body must match.
+    pub fn body(mut self, body: bool) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Whether session ids, element references, and shadow root references must match exactly.
+    pub fn exact_ids(mut self, exact: bool) -> Self {
+        self.ids = exact;
+        self
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        if self.method && recorded.method != request.method {
+            return false;
+        }
+        if self.endpoint {
+            let matches = if self.ids {
+                recorded.path == request.path
+            } else {
+                normalize_path(&recorded.path) == normalize_path(&request.path)
+            };
+            if !matches {
+                return false;
+            }
+        }
+        if self.body {
+            let matches = if self.ids {
+                recorded.body == request.body
+            } else {
+                recorded.body.clone().map(normalize_body)
+                    == request.body.clone().map(normalize_body)
+            };
+            if !matches {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Replace the session id, element references, and shadow root references in `path`.
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    for i in 1..segments.len() {
+        match (segments[i - 1], segments[i]) {
+            ("session", _) => segments[i] = "{session}",
+            ("element", "active") => {}
+            ("element", _) => segments[i] = "{element}",
+            ("shadow", _) => segments[i] = "{shadow}",
+            _ => {}
+        }
+    }
+    segments.join("/")
+}
+
+/// Replace element references and shadow root references anywhere in `body`.
+fn normalize_body(body: Json) -> Json {
+    match body {
+        Json::Object(o) => Json::Object(
+            o.into_iter()
+                .map(|(k, v)| {
+                    if k == ELEMENT_KEY || k == SHADOW_KEY {
+                        (k, Json::Null)
+                    } else {
+                        (k, normalize_body(v))
+                    }
+                })
+                .collect(),
+        ),
+        Json::Array(a) => Json::Array(a.into_iter().map(normalize_body).collect()),
+        v => v,
+    }
+}
+
+#[derive(Debug)]
+struct Tape {
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+/// A [`Transport`] that answers requests from a [`Cassette`], without contacting any server.
+///
+/// Each request is answered with the response of the first recorded interaction that matches it
+/// according to the [`MatchRules`], and that has not already been played back. A request that
+/// does not match any remaining interaction fails with
+/// [`CmdError::NotRecorded`](error::CmdError::NotRecorded). That is not a transport error, so a
+/// [`RetryPolicy`](crate::wd::RetryPolicy) does not retry it.
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    tape: Arc<Mutex<Tape>>,
+    rules: MatchRules,
+}
+
+impl Replayer {
+    /// Replay the given cassette using the default [`MatchRules`].
+    pub fn new(cassette: Cassette) -> Self {
+        Self::with_rules(cassette, MatchRules::default())
+    }
+
+    /// Replay the given cassette using the given [`MatchRules`].
+    pub fn with_rules(cassette: Cassette, rules: MatchRules) -> Self {
+        let played = vec![false; cassette.interactions.len()];
+        Replayer {
+            tape: Arc::new(Mutex::new(Tape {
+                interactions: cassette.interactions,
+                played,
+            })),
+            rules,
+        }
+    }
+
+    /// The recorded interactions that have not (yet) been played back.
+    pub fn remaining(&self) -> Vec<Interaction> {
+        let tape = self.tape.lock().unwrap();
+        tape.interactions
+            .iter()
+            .zip(&tape.played)
+            .filter(|(_, &played)| !played)
+            .map(|(interaction, _)| interaction.clone())
+            .collect()
+    }
+}
+
+impl Transport for Replayer {
+    fn send(&self, request: WireRequest, _: Next) -> TransportFuture {
+        let request = RecordedRequest::from_wire(&request);
+        let mut tape = self.tape.lock().unwrap();
+        let Tape {
+            ref interactions,
+            ref mut played,
+        } = *tape;
+        let found = interactions
+            .iter()
+            .zip(played.iter_mut())
+            .find(|(interaction, played)| {
+                !**played && self.rules.matches(&interaction.request, &request)
+            });
+        let response = match found {
+            Some((interaction, played)) => {
+                *played = true;
+                Ok(interaction.response.to_wire())
+            }
+            None => Err(error::CmdError::NotRecorded(format!(
+                "{} {}",
+                request.method, request.path
+            ))),
+        };
+        Box::pin(async move { response })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ids_are_ignored_by_default() {
+        let recorded = RecordedRequest {
+            method: "POST".to_string(),
+            path: "/session/abc/element/e1/click".to_string(),
+            body: Some(json!({ "id": { ELEMENT_KEY: "e1" } })),
+        };
+        let request = RecordedRequest {
+            method: "POST".to_string(),
+            path: "/session/def/element/e2/click".to_string(),
+            body: Some(json!({ "id": { ELEMENT_KEY: "e2" } })),
+        };
+        assert!(MatchRules::default().matches(&recorded, &request));
+        assert!(!MatchRules::default()
+            .exact_ids(true)
+            .matches(&recorded, &request));
+
+        let other = RecordedRequest {
+            path: "/session/def/element/e2/clear".to_string(),
+            ..request
+        };
+        assert!(!MatchRules::default().matches(&recorded, &other));
+        assert!(MatchRules::default()
+            .endpoint(false)
+            .matches(&recorded, &other));
+    }
+
+    #[test]
+    fn active_element_is_not_an_id() {
+        assert_eq!(
+            normalize_path("/session/abc/element/active"),
+            "/session/{session}/element/active"
+        );
+        assert_eq!(
+            normalize_path("/wd/hub/session/abc/shadow/s1/elements"),
+            "/wd/hub/session/{session}/shadow/{shadow}/elements"
+        );
+    }
+}
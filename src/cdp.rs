@@ -0,0 +1,245 @@
+//! Chrome DevTools Protocol commands for Chromium-based browsers.
+//!
+//! chromedriver and msedgedriver can pass [Chrome DevTools Protocol][1] (CDP) commands straight
+//! through to the browser, which gives access to features that WebDriver does not cover, such as
+//! network throttling and geolocation. [`Client::cdp`] sends any CDP command, and the other
+//! methods in this module wrap some common ones:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), fantoccini::error::CmdError> {
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let c = fantoccini::ClientBuilder::native().connect("http://localhost:9515").await.expect("failed to connect to WebDriver");
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = fantoccini::ClientBuilder::rustls().expect("rustls initialization").connect("http://localhost:9515").await.expect("failed to connect to WebDriver");
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! use fantoccini::cdp::NetworkConditions;
+//! use std::time::Duration;
+//!
+//! c.cdp_set_timezone_override("Europe/Oslo").await?;
+//! c.cdp_emulate_network_conditions(NetworkConditions {
+//!     latency: Duration::from_millis(300),
+//!     download_throughput: Some(50_000),
+//!     ..Default::default()
+//! })
+//! .await?;
+//! c.goto("https://www.wikipedia.org/").await?;
+//! # c.close().await
+//! # }
+//! ```
+//!
+//! These commands only work with Chromium-based drivers. With other drivers, they fail with an
+//! [`UnsupportedOperation`](crate::error::ErrorStatus::UnsupportedOperation) error without
+//! contacting the driver.
+//!
+//! [1]: https://chromedevtools.github.io/devtools-protocol/
+use crate::error;
+use crate::wd::WebDriverCompatibleCommand;
+use crate::Client;
+use base64::Engine;
+use http::Method;
+use serde_json::{json, Value as Json};
+use std::time::Duration;
+use url::{ParseError, Url};
+
+/// A CDP command sent through the driver's vendor-specific passthrough endpoint.
+#[derive(Debug)]
+struct CdpCommand {
+    /// The vendor prefix of the endpoint, `goog` for Chrome and `ms` for Edge.
+    vendor: &'static str,
+    method: String,
+    params: Json,
+}
+
+impl WebDriverCompatibleCommand for CdpCommand {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        base_url.join(&format!(
+            "session/{}/{}/cdp/execute",
+            session_id.unwrap_or_default(),
+            self.vendor
+        ))
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        let body = json!({ "cmd": self.method, "params": self.params });
+        (Method::POST, Some(body.to_string()))
+    }
+}
+
+/// Network conditions to emulate with [`Client::cdp_emulate_network_conditions`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkConditions {
+    /// Whether to behave as if the network is down.
+    pub offline: bool,
+    /// The latency to add to every request.
+    pub latency: Duration,
+    /// The maximum download rate, in bytes per second, or `None` for no limit.
+    pub download_throughput: Option<u64>,
+    /// The maximum upload rate, in bytes per second, or `None` for no limit.
+    pub upload_throughput: Option<u64>,
+}
+
+/// Screen metrics to emulate with [`Client::cdp_set_device_metrics_override`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceMetrics {
+    /// The width of the viewport, in CSS pixels.
+    pub width: u32,
+    /// The height of the viewport, in CSS pixels.
+    pub height: u32,
+    /// The number of device pixels per CSS pixel, or 0 to keep the browser's own.
+    pub device_scale_factor: f64,
+    /// Whether to emulate a mobile device, which affects things like the meta viewport tag and
+    /// overlay scrollbars.
+    pub mobile: bool,
+}
+
+/// [Chrome DevTools Protocol](crate::cdp) commands.
+impl Client {
+    /// Work out which vendor's CDP endpoint the driver has, if any.
+    fn cdp_vendor(&self) -> Result<&'static str, error::CmdError> {
+        let Some(caps) = self.capabilities() else {
+            // we don't know what the driver is, so let it tell us if it doesn't support CDP
+            return Ok("goog");
+        };
+        if caps.contains_key("ms:edgeOptions") {
+            return Ok("ms");
+        }
+        if caps.contains_key("goog:chromeOptions") {
+            return Ok("goog");
+        }
+        let browser = caps
+            .get("browserName")
+            .and_then(Json::as_str)
+            .unwrap_or("an unknown browser");
+        match &*browser.to_ascii_lowercase() {
+            "msedge" | "microsoftedge" => Ok("ms"),
+            "chrome" | "chromium" | "chrome-headless-shell" => Ok("goog"),
+            _ => Err(error::CmdError::Standard(error::WebDriver::new(
+                error::ErrorStatus::UnsupportedOperation,
+                format!(
+                    "Chrome DevTools Protocol commands need a Chromium-based browser, \
+                     but this session is using {}",
+                    browser
+                ),
+            ))),
+        }
+    }
+
+    /// Run the [Chrome DevTools Protocol](crate::cdp) command `method` with the given `params`,
+    /// and return its result.
+    ///
+    /// `method` is the fully qualified name of the command, such as `"Page.reload"`, and `params`
+    /// is a JSON object with its parameters. See the
+    /// [protocol documentation](https://chromedevtools.github.io/devtools-protocol/) for the
+    /// available commands.
+    pub async fn cdp(&self, method: &str, params: Json) -> Result<Json, error::CmdError> {
+        let vendor = self.cdp_vendor()?;
+        self.issue_cmd(CdpCommand {
+            vendor,
+            method: method.to_string(),
+            params,
+        })
+        .await
+    }
+
+    /// Emulate the given network conditions, using
+    /// [`Network.emulateNetworkConditions`](https://chromedevtools.github.io/devtools-protocol/tot/Network/#method-emulateNetworkConditions).
+    pub async fn cdp_emulate_network_conditions(
+        &self,
+        conditions: NetworkConditions,
+    ) -> Result<(), error::CmdError> {
+        let throughput = |limit: Option<u64>| limit.map_or(-1.0, |limit| limit as f64);
+        self.cdp(
+            "Network.emulateNetworkConditions",
+            json!({
+                "offline": conditions.offline,
+                "latency": conditions.latency.as_secs_f64() * 1000.0,
+                "downloadThroughput": throughput(conditions.download_throughput),
+                "uploadThroughput": throughput(conditions.upload_throughput),
+            }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Report the given position, in degrees, with the given accuracy, in meters, to the
+    /// [Geolocation API](https://developer.mozilla.org/en-US/docs/Web/API/Geolocation_API), using
+    /// [`Emulation.setGeolocationOverride`](https://chromedevtools.github.io/devtools-protocol/tot/Emulation/#method-setGeolocationOverride).
+    ///
+    /// Pages still need permission to read the position.
+    pub async fn cdp_set_geolocation_override(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        accuracy: f64,
+    ) -> Result<(), error::CmdError> {
+        self.cdp(
+            "Emulation.setGeolocationOverride",
+            json!({
+                "latitude": latitude,
+                "longitude": longitude,
+                "accuracy": accuracy,
+            }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Use the time zone with the given [IANA name](https://www.iana.org/time-zones), such as
+    /// `"America/New_York"`, using
+    /// [`Emulation.setTimezoneOverride`](https://chromedevtools.github.io/devtools-protocol/tot/Emulation/#method-setTimezoneOverride).
+    pub async fn cdp_set_timezone_override(&self, timezone: &str) -> Result<(), error::CmdError> {
+        self.cdp(
+            "Emulation.setTimezoneOverride",
+            json!({ "timezoneId": timezone }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Emulate a screen with the given metrics, using
+    /// [`Emulation.setDeviceMetricsOverride`](https://chromedevtools.github.io/devtools-protocol/tot/Emulation/#method-setDeviceMetricsOverride).
+    pub async fn cdp_set_device_metrics_override(
+        &self,
+        metrics: DeviceMetrics,
+    ) -> Result<(), error::CmdError> {
+        self.cdp(
+            "Emulation.setDeviceMetricsOverride",
+            json!({
+                "width": metrics.width,
+                "height": metrics.height,
+                "deviceScaleFactor": metrics.device_scale_factor,
+                "mobile": metrics.mobile,
+            }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Take a PNG screenshot of the current page, using
+    /// [`Page.captureScreenshot`](https://chromedevtools.github.io/devtools-protocol/tot/Page/#method-captureScreenshot).
+    ///
+    /// If `beyond_viewport` is `true`, the screenshot covers the whole page rather than just the
+    /// part that is currently visible.
+    pub async fn cdp_capture_screenshot(
+        &self,
+        beyond_viewport: bool,
+    ) -> Result<Vec<u8>, error::CmdError> {
+        let res = self
+            .cdp(
+                "Page.captureScreenshot",
+                json!({
+                    "format": "png",
+                    "captureBeyondViewport": beyond_viewport,
+                }),
+            )
+            .await?;
+        match res.get("data").and_then(Json::as_str) {
+            Some(data) => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(error::CmdError::ImageDecodeError),
+            None => Err(error::CmdError::NotW3C(res)),
+        }
+    }
+}
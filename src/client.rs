@@ -1,13 +1,14 @@
 //! WebDriver client implementation.
 
+use crate::accessibility::{normalize_whitespace, Computed};
 use crate::actions::Actions;
 use crate::elements::{Element, Form};
 use crate::error;
 use crate::session::{Cmd, Session, Task};
 use crate::wait::Wait;
 use crate::wd::{
-    Capabilities, Locator, NewSessionResponse, NewWindowType, PrintConfiguration,
-    TimeoutConfiguration, WebDriverStatus, WindowHandle,
+    Capabilities, Locator, Lookup, NewSessionResponse, NewWindowType, PrintConfiguration,
+    SessionCapabilities, SessionDescriptor, TimeoutConfiguration, WebDriverStatus, WindowHandle,
 };
 use base64::Engine;
 use http::Method;
@@ -39,6 +40,9 @@ use http_body_util::combinators::BoxBody;
 pub struct Client {
     pub(crate) tx: mpsc::UnboundedSender<Task>,
     pub(crate) new_session_response: Option<NewSessionResponse>,
+    pub(crate) timeout: Option<std::time::Duration>,
+    #[cfg(feature = "bidi")]
+    pub(crate) bidi: Option<crate::bidi::BiDi>,
 }
 
 impl Client {
@@ -53,22 +57,6 @@ impl Client {
         ClientBuilder::native().connect(webdriver).await
     }
 
-    /// Connect to the WebDriver host running the given address.
-    ///
-    /// The provided `connector` is used to establish the connection to the WebDriver host, and
-    /// should generally be one that supports HTTPS, as that is commonly required by WebDriver
-    /// implementations.
-    ///
-    pub(crate) async fn new_with_connector<C>(
-        webdriver: &str,
-        connector: C,
-    ) -> Result<Self, error::NewSessionError>
-    where
-        C: connect::Connect + Unpin + 'static + Clone + Send + Sync,
-    {
-        Session::with_capabilities_and_connector(webdriver, &Default::default(), connector).await
-    }
-
     /// Reconnect to a previously established WebDriver session using its ID.
     ///
     /// Ideal for resuming operations without losing session data after a disconnect
@@ -83,7 +71,7 @@ impl Client {
         C: connect::Connect + Unpin + 'static + Clone + Send + Sync,
     {
         let (client, wdb) = Session::create_client_and_parse_url(webdriver, connector).await?;
-        Session::setup_session(client, wdb, Some(session_id)).await
+        Session::setup_session(client, wdb, Some(session_id), Default::default()).await
     }
 
     /// Connect to the WebDriver host running the given address.
@@ -102,7 +90,8 @@ impl Client {
     where
         C: connect::Connect + Unpin + 'static + Clone + Send + Sync,
     {
-        Session::with_capabilities_and_connector(webdriver, cap, connector).await
+        Session::with_capabilities_and_connector(webdriver, cap, &[], connector, Default::default())
+            .await
     }
 
     /// Get the unique session ID assigned by the WebDriver server to this client.
@@ -129,6 +118,20 @@ impl Client {
         }
     }
 
+    /// Get a view of this client whose commands time out after `timeout`.
+    ///
+    /// Each request that the returned client (and any elements found through it) sends to the
+    /// WebDriver server fails with [`CmdError::RequestTimeout`](error::CmdError::RequestTimeout)
+    /// if the server does not respond within `timeout`. This overrides the default set with
+    /// [`ClientBuilder::request_timeout`] for those commands. The returned client shares its
+    /// session with `self`.
+    pub fn with_timeout(&self, timeout: std::time::Duration) -> Client {
+        Client {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
     /// Get the response obtained when opening the session.
     ///
     /// Returns `None` if no session has yet been opened.
@@ -143,6 +146,31 @@ impl Client {
         self.new_session_response.as_ref()?.capabilities()
     }
 
+    /// Get a typed view of the capabilities returned by the remote end when opening the session.
+    ///
+    /// Returns `None` if no session has yet been opened.
+    pub fn session_capabilities(&self) -> Option<SessionCapabilities> {
+        self.new_session_response.as_ref()?.session_capabilities()
+    }
+
+    /// Get the index of the [`firstMatch`](crate::ClientBuilder::first_match) alternative that
+    /// was used to open the session.
+    ///
+    /// See [`NewSessionResponse::matched_alternative`].
+    pub fn matched_alternative(&self) -> Option<usize> {
+        self.new_session_response.as_ref()?.matched_alternative()
+    }
+
+    /// Get the WebDriver BiDi connection for this session.
+    ///
+    /// Returns `None` unless the `webSocketUrl` capability was requested when opening the
+    /// session, and the remote end supports it.
+    #[cfg(feature = "bidi")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bidi")))]
+    pub fn bidi(&self) -> Option<&crate::bidi::BiDi> {
+        self.bidi.as_ref()
+    }
+
     /// Terminate the WebDriver session.
     ///
     /// Normally, a shutdown of the WebDriver connection will be initiated when the last clone of a
@@ -155,7 +183,13 @@ impl Client {
     ///
     /// This function may be useful in conjunction with `raw_client_for`, as it allows you to close
     /// the automated browser window while doing e.g., a large download.
+    ///
+    /// If the session has a WebDriver BiDi connection, that is closed too.
     pub async fn close(self) -> Result<(), error::CmdError> {
+        #[cfg(feature = "bidi")]
+        if let Some(ref bidi) = self.bidi {
+            bidi.close().await;
+        }
         self.issue(Cmd::Shutdown).await?;
         Ok(())
     }
@@ -175,6 +209,33 @@ impl Client {
         Ok(())
     }
 
+    /// Leave this client's session running, and describe it so that it can be taken over later.
+    ///
+    /// This [persists](Client::persist) the session, and returns a [`SessionDescriptor`] with
+    /// everything [`ClientBuilder::attach`] needs to create a new `Client` for the same session,
+    /// possibly in another process.
+    pub async fn detach(self) -> Result<SessionDescriptor, error::CmdError> {
+        let session_id = self.session_id().await?.ok_or_else(|| {
+            error::CmdError::Lost(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "client has no session to detach",
+            ))
+        })?;
+        let webdriver = match self.issue(Cmd::GetWebDriverUrl).await? {
+            Json::String(s) => s,
+            v => unreachable!("response to GetWebDriverUrl was not a string: {:?}", v),
+        };
+        let timeouts = self.get_timeouts().await?;
+        self.persist().await?;
+        Ok(SessionDescriptor::from_parts(
+            webdriver,
+            session_id,
+            self.capabilities().cloned(),
+            self.get_ua().await?,
+            timeouts,
+        ))
+    }
+
     /// Create a new raw request builder.
     ///
     /// This method allows to build a direct HTTP request to a remote site without routing
@@ -184,6 +245,7 @@ impl Client {
     ///
     /// This can be useful for operations where direct access is needed or when
     /// interacting with third-party services that require the same session cookies.
+    #[allow(clippy::type_complexity)]
     pub fn raw_request(
         &self,
     ) -> RawRequestBuilder<
@@ -834,7 +896,7 @@ impl Client {
     /// standard.
     #[cfg_attr(docsrs, doc(alias = "Find Element"))]
     pub async fn find(&self, search: Locator<'_>) -> Result<Element, error::CmdError> {
-        self.by(search.into_parameters()).await
+        self.locate(SearchRoot::Document, search).await
     }
 
     /// Find all elements on the page that match the given [`Locator`].
@@ -843,17 +905,7 @@ impl Client {
     /// standard.
     #[cfg_attr(docsrs, doc(alias = "Find Elements"))]
     pub async fn find_all(&self, search: Locator<'_>) -> Result<Vec<Element>, error::CmdError> {
-        let res = self
-            .issue(WebDriverCommand::FindElements(search.into_parameters()))
-            .await?;
-        let array = self.parse_lookup_all(res)?;
-        Ok(array
-            .into_iter()
-            .map(move |e| Element {
-                client: self.clone(),
-                element: e,
-            })
-            .collect())
+        self.locate_all(SearchRoot::Document, search).await
     }
 
     /// Get the active element for this session.
@@ -881,12 +933,10 @@ impl Client {
     ///
     /// Through the returned `Form`, HTML forms can be filled out and submitted.
     pub async fn form(&self, search: Locator<'_>) -> Result<Form, error::CmdError> {
-        let l = search.into_parameters();
-        let res = self.issue(WebDriverCommand::FindElement(l)).await?;
-        let f = self.parse_lookup(res)?;
+        let f = self.locate(SearchRoot::Document, search).await?;
         Ok(Form {
             client: self.clone(),
-            form: f,
+            form: f.element,
         })
     }
 }
@@ -1249,18 +1299,6 @@ impl Client {
 
 /// Helper methods
 impl Client {
-    pub(crate) async fn by(
-        &self,
-        locator: webdriver::command::LocatorParameters,
-    ) -> Result<Element, error::CmdError> {
-        let res = self.issue(WebDriverCommand::FindElement(locator)).await?;
-        let e = self.parse_lookup(res)?;
-        Ok(Element {
-            client: self.clone(),
-            element: e,
-        })
-    }
-
     /// Extract the `WebElement` from a `FindElement` or `FindElementElement` command.
     pub(crate) fn parse_lookup(
         &self,
@@ -1308,6 +1346,155 @@ impl Client {
     }
 }
 
+/// Where to look for elements that match a [`Locator`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SearchRoot<'a> {
+    /// The document of the current browsing context.
+    Document,
+    /// The descendants of an element.
+    Element(&'a webdriver::common::WebElement),
+    /// The descendants of a shadow root.
+    ShadowRoot(&'a webdriver::common::ShadowRoot),
+}
+
+/// Element lookup
+impl Client {
+    /// Find the first element below `root` that matches `search`.
+    pub(crate) async fn locate(
+        &self,
+        root: SearchRoot<'_>,
+        search: Locator<'_>,
+    ) -> Result<Element, error::CmdError> {
+        let params = match search.into_lookup() {
+            Lookup::Native(params) => params,
+            Lookup::Script { script, args } => {
+                let found = self.locate_by_script(root, search, script, args, 1).await?;
+                return found.into_iter().next().ok_or_else(|| {
+                    error::CmdError::Standard(error::WebDriver::new(
+                        error::ErrorStatus::NoSuchElement,
+                        format!("no element matches {:?}", search),
+                    ))
+                });
+            }
+        };
+        let cmd = match root {
+            SearchRoot::Document => WebDriverCommand::FindElement(params),
+            SearchRoot::Element(e) => WebDriverCommand::FindElementElement(e.clone(), params),
+            SearchRoot::ShadowRoot(s) => WebDriverCommand::FindShadowRootElement(s.clone(), params),
+        };
+        let res = self.issue(cmd).await?;
+        let e = self.parse_lookup(res)?;
+        Ok(Element {
+            client: self.clone(),
+            element: e,
+        })
+    }
+
+    /// Find all elements below `root` that match `search`.
+    pub(crate) async fn locate_all(
+        &self,
+        root: SearchRoot<'_>,
+        search: Locator<'_>,
+    ) -> Result<Vec<Element>, error::CmdError> {
+        let params = match search.into_lookup() {
+            Lookup::Native(params) => params,
+            Lookup::Script { script, args } => {
+                return self
+                    .locate_by_script(root, search, script, args, usize::MAX)
+                    .await;
+            }
+        };
+        let cmd = match root {
+            SearchRoot::Document => WebDriverCommand::FindElements(params),
+            SearchRoot::Element(e) => WebDriverCommand::FindElementElements(e.clone(), params),
+            SearchRoot::ShadowRoot(s) => {
+                WebDriverCommand::FindShadowRootElements(s.clone(), params)
+            }
+        };
+        let res = self.issue(cmd).await?;
+        let array = self.parse_lookup_all(res)?;
+        Ok(array
+            .into_iter()
+            .map(move |e| Element {
+                client: self.clone(),
+                element: e,
+            })
+            .collect())
+    }
+
+    /// Find up to `limit` elements that match `search` by running its lookup `script`.
+    async fn locate_by_script(
+        &self,
+        root: SearchRoot<'_>,
+        search: Locator<'_>,
+        script: &str,
+        mut args: Vec<Json>,
+        limit: usize,
+    ) -> Result<Vec<Element>, error::CmdError> {
+        let root = match root {
+            SearchRoot::Document => Json::Null,
+            SearchRoot::Element(e) => via_json!(e),
+            SearchRoot::ShadowRoot(s) => via_json!(s),
+        };
+        args.insert(0, root);
+        let res = self.execute(script, args).await?;
+
+        let Locator::Role { role, name } = search else {
+            let mut found = self.parse_lookup_all(res)?;
+            found.truncate(limit);
+            return Ok(found
+                .into_iter()
+                .map(|e| Element {
+                    client: self.clone(),
+                    element: e,
+                })
+                .collect());
+        };
+
+        // the role script returns candidates along with an approximation of their names, which
+        // the driver's computed role and label take precedence over where they are available
+        let candidates = match res {
+            Json::Array(candidates) => candidates,
+            res => return Err(error::CmdError::NotW3C(res)),
+        };
+        let name = name.map(normalize_whitespace);
+        let mut computed = Computed::default();
+        let mut found = Vec::new();
+        for candidate in candidates {
+            if found.len() >= limit {
+                break;
+            }
+            let (element, approximate_name) = match candidate {
+                Json::Array(ref pair) if pair.len() == 2 => {
+                    (self.parse_lookup(pair[0].clone())?, pair[1].as_str())
+                }
+                _ => return Err(error::CmdError::NotW3C(candidate)),
+            };
+            let element = Element {
+                client: self.clone(),
+                element,
+            };
+
+            if let Some(computed_role) = computed.role(&element).await? {
+                if !computed_role.eq_ignore_ascii_case(role) {
+                    continue;
+                }
+            }
+            if let Some(ref name) = name {
+                let actual = match computed.label(&element).await? {
+                    Some(label) => Some(label),
+                    None => approximate_name.map(normalize_whitespace),
+                };
+                if actual.as_ref() != Some(name) {
+                    continue;
+                }
+            }
+            found.push(element);
+        }
+        Ok(found)
+    }
+}
+
 /// Response returned by [`Client::new_window()`] method.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct NewWindowResponse {
@@ -0,0 +1,258 @@
+//! Launching and managing a local WebDriver process.
+//!
+//! Running tests against a local browser usually means starting a driver such as
+//! [`geckodriver`](https://github.com/mozilla/geckodriver) or
+//! [`chromedriver`](https://chromedriver.chromium.org/) on a free port, waiting for it to come
+//! up, and making sure it goes away again afterwards. [`DriverService`] does all of that, and
+//! hands back a [`ClientBuilder`] that connects to the driver it started:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::driver::DriverService;
+//! use fantoccini::ClientBuilder;
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let builder = ClientBuilder::native();
+//! # #[cfg(feature = "rustls-tls")]
+//! # let builder = ClientBuilder::rustls()?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let builder: ClientBuilder<hyper_util::client::legacy::connect::HttpConnector> = unreachable!();
+//! let c = DriverService::geckodriver()
+//!     .log_to(|line| eprintln!("geckodriver: {}", line))
+//!     .start(builder)
+//!     .await?
+//!     .connect_driver()
+//!     .await?;
+//! c.goto("https://www.wikipedia.org/").await?;
+//!
+//! // geckodriver exits once the last client for it is closed or dropped
+//! c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::session::Session;
+use crate::ClientBuilder;
+use hyper_util::client::legacy::connect;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+
+/// How often the driver is asked whether it is ready while it starts up.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type LogSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// A launcher for a local WebDriver process.
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Clone)]
+pub struct DriverService {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    port: Option<u16>,
+    log: Option<LogSink>,
+    startup_timeout: Duration,
+}
+
+impl fmt::Debug for DriverService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DriverService")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("envs", &self.envs)
+            .field("port", &self.port)
+            .field("log", &self.log.is_some())
+            .field("startup_timeout", &self.startup_timeout)
+            .finish()
+    }
+}
+
+impl DriverService {
+    /// Launch the driver binary `program`.
+    ///
+    /// If `program` is not a path, it is looked up on `PATH`. The driver must accept a
+    /// `--port=<port>` argument, as `geckodriver`, `chromedriver`, and `msedgedriver` all do.
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        DriverService {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            port: None,
+            log: None,
+            startup_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Launch `geckodriver` from `PATH`.
+    pub fn geckodriver() -> Self {
+        Self::new("geckodriver")
+    }
+
+    /// Launch `chromedriver` from `PATH`.
+    pub fn chromedriver() -> Self {
+        Self::new("chromedriver")
+    }
+
+    /// Launch `msedgedriver` from `PATH`.
+    pub fn msedgedriver() -> Self {
+        Self::new("msedgedriver")
+    }
+
+    /// Pass an additional argument to the driver.
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Set an environment variable for the driver.
+    pub fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.envs
+            .push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Run the driver on the given port.
+    ///
+    /// By default, a free port is picked.
+    pub fn port(&mut self, port: u16) -> &mut Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Forward every line the driver writes to its stdout or stderr to `sink`.
+    ///
+    /// By default, the driver's output is discarded.
+    pub fn log_to<F>(&mut self, sink: F) -> &mut Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.log = Some(Arc::new(sink));
+        self
+    }
+
+    /// How long to wait for the driver to report that it is ready.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn startup_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Start the driver, and wait until it reports that it is [ready](crate::Client::status).
+    ///
+    /// On success, `builder` is returned pointed at the new driver, so that
+    /// [`ClientBuilder::connect_driver`] connects to it. The driver process is terminated once
+    /// that builder and every [`Client`](crate::Client) connected through it have been closed or
+    /// dropped.
+    ///
+    /// Fails if the driver cannot be started, if it exits before it is ready, or if it does not
+    /// become ready within the [startup timeout](DriverService::startup_timeout).
+    pub async fn start<C>(&self, mut builder: ClientBuilder<C>) -> io::Result<ClientBuilder<C>>
+    where
+        C: connect::Connect + Send + Sync + Clone + Unpin + 'static,
+    {
+        let port = match self.port {
+            Some(port) => port,
+            None => std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+                .local_addr()?
+                .port(),
+        };
+
+        let output = || {
+            if self.log.is_some() {
+                std::process::Stdio::piped()
+            } else {
+                std::process::Stdio::null()
+            }
+        };
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(format!("--port={}", port))
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(std::process::Stdio::null())
+            .stdout(output())
+            .stderr(output())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(ref log) = self.log {
+            if let Some(stdout) = child.stdout.take() {
+                forward(stdout, Arc::clone(log));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                forward(stderr, Arc::clone(log));
+            }
+        }
+
+        let url = format!("http://{}:{}/", Ipv4Addr::LOCALHOST, port);
+        let (client, wdb) = Session::create_client_and_parse_url(&url, builder.connector.clone())
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let status = Session::setup_session(client, wdb, None, Default::default())
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        let deadline = Instant::now() + self.startup_timeout;
+        loop {
+            if let Some(exit) = child.try_wait()? {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "{} exited during startup with {}",
+                        self.program.to_string_lossy(),
+                        exit
+                    ),
+                ));
+            }
+            // a driver that accepts connections but never answers must not hang us either
+            let left = deadline.saturating_duration_since(Instant::now());
+            match tokio::time::timeout(left, status.status()).await {
+                Ok(Ok(status)) if status.ready => break,
+                Ok(_) => {}
+                Err(_) => return Err(self.not_ready()),
+            }
+            if Instant::now() >= deadline {
+                return Err(self.not_ready());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        builder.config.driver = Some(Arc::new(Process { url, _child: child }));
+        Ok(builder)
+    }
+
+    /// The error for a driver that did not become ready within the startup timeout.
+    fn not_ready(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "{} did not become ready within {:?}",
+                self.program.to_string_lossy(),
+                self.startup_timeout
+            ),
+        )
+    }
+}
+
+/// A running driver process, which is killed when dropped.
+#[derive(Debug)]
+pub(crate) struct Process {
+    pub(crate) url: String,
+    _child: Child,
+}
+
+/// Pass each line of `output` to `sink` until the process closes it.
+fn forward(output: impl AsyncRead + Unpin + Send + 'static, sink: LogSink) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            sink(&line);
+        }
+    });
+}
@@ -1,12 +1,16 @@
 //! Types used to represent particular elements on a page.
 
+use crate::client::SearchRoot;
 use crate::wd::Locator;
 use crate::{error, Client};
 use base64::Engine;
 use serde::Serialize;
 use serde_json::Value as Json;
 use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
 use std::ops::Deref;
+use std::path::Path;
 use webdriver::command::WebDriverCommand;
 use webdriver::common::{FrameId, SHADOW_KEY};
 
@@ -159,18 +163,9 @@ impl ShadowRoot {
     /// of the WebDriver standard.
     #[cfg_attr(docsrs, doc(alias = "Find Element From Shadow Root"))]
     pub async fn find(&self, search: Locator<'_>) -> Result<Element, error::CmdError> {
-        let res = self
-            .client
-            .issue(WebDriverCommand::FindShadowRootElement(
-                self.shadow_root.clone(),
-                search.into_parameters(),
-            ))
-            .await?;
-        let e = self.client.parse_lookup(res)?;
-        Ok(Element {
-            client: self.client.clone(),
-            element: e,
-        })
+        self.client
+            .locate(SearchRoot::ShadowRoot(&self.shadow_root), search)
+            .await
     }
 
     /// Find all elements within the shadow root that match the given [`Locator`].
@@ -179,21 +174,9 @@ impl ShadowRoot {
     /// of the WebDriver standard.
     #[cfg_attr(docsrs, doc(alias = "Find Elements From Shadow Root"))]
     pub async fn find_all(&self, search: Locator<'_>) -> Result<Vec<Element>, error::CmdError> {
-        let res = self
-            .client
-            .issue(WebDriverCommand::FindShadowRootElements(
-                self.shadow_root.clone(),
-                search.into_parameters(),
-            ))
-            .await?;
-        let array = self.client.parse_lookup_all(res)?;
-        Ok(array
-            .into_iter()
-            .map(move |e| Element {
-                client: self.client.clone(),
-                element: e,
-            })
-            .collect())
+        self.client
+            .locate_all(SearchRoot::ShadowRoot(&self.shadow_root), search)
+            .await
     }
 }
 
@@ -231,18 +214,9 @@ impl Element {
     /// standard.
     #[cfg_attr(docsrs, doc(alias = "Find Element From Element"))]
     pub async fn find(&self, search: Locator<'_>) -> Result<Element, error::CmdError> {
-        let res = self
-            .client
-            .issue(WebDriverCommand::FindElementElement(
-                self.element.clone(),
-                search.into_parameters(),
-            ))
-            .await?;
-        let e = self.client.parse_lookup(res)?;
-        Ok(Element {
-            client: self.client.clone(),
-            element: e,
-        })
+        self.client
+            .locate(SearchRoot::Element(&self.element), search)
+            .await
     }
 
     /// Find all descendant elements that match the given [`Locator`].
@@ -252,21 +226,9 @@ impl Element {
     /// standard.
     #[cfg_attr(docsrs, doc(alias = "Find Elements From Element"))]
     pub async fn find_all(&self, search: Locator<'_>) -> Result<Vec<Element>, error::CmdError> {
-        let res = self
-            .client
-            .issue(WebDriverCommand::FindElementElements(
-                self.element.clone(),
-                search.into_parameters(),
-            ))
-            .await?;
-        let array = self.client.parse_lookup_all(res)?;
-        Ok(array
-            .into_iter()
-            .map(move |e| Element {
-                client: self.client.clone(),
-                element: e,
-            })
-            .collect())
+        self.client
+            .locate_all(SearchRoot::Element(&self.element), search)
+            .await
     }
 }
 
@@ -429,6 +391,36 @@ impl Element {
         }
     }
 
+    /// Get the computed [WAI-ARIA role] of this element, such as `"button"` or `"heading"`.
+    ///
+    /// See [12.4.9 Get Computed Role](https://www.w3.org/TR/webdriver2/#get-computed-role) of the
+    /// WebDriver standard.
+    ///
+    /// [WAI-ARIA role]: https://www.w3.org/TR/wai-aria/#introroles
+    #[cfg_attr(docsrs, doc(alias = "Get Computed Role"))]
+    pub async fn computed_role(&self) -> Result<String, error::CmdError> {
+        let cmd = WebDriverCommand::GetComputedRole(self.element.clone());
+        match self.client.issue(cmd).await? {
+            Json::String(v) => Ok(v),
+            v => Err(error::CmdError::NotW3C(v)),
+        }
+    }
+
+    /// Get the computed [accessible name] of this element.
+    ///
+    /// See [12.4.10 Get Computed Label](https://www.w3.org/TR/webdriver2/#get-computed-label) of
+    /// the WebDriver standard.
+    ///
+    /// [accessible name]: https://www.w3.org/TR/accname/
+    #[cfg_attr(docsrs, doc(alias = "Get Computed Label"))]
+    pub async fn computed_label(&self) -> Result<String, error::CmdError> {
+        let cmd = WebDriverCommand::GetComputedLabel(self.element.clone());
+        match self.client.issue(cmd).await? {
+            Json::String(v) => Ok(v),
+            v => Err(error::CmdError::NotW3C(v)),
+        }
+    }
+
     /// Retrieve the HTML contents of this element.
     ///
     /// `inner` dictates whether the wrapping node's HTML is excluded or not. For example, take the
@@ -504,6 +496,80 @@ impl Element {
             Err(error::CmdError::NotW3C(r))
         }
     }
+
+    /// Select the files at `paths` in this `<input type="file">` element.
+    ///
+    /// The files are read on the machine that fantoccini runs on. With the `remote-upload`
+    /// feature, when the session is on a Selenium server, which is recognized by its `se:`
+    /// capabilities, each file is first sent to the server, so this works even if the browser
+    /// runs on another machine, such as a Selenium Grid node. Otherwise the driver is given the
+    /// absolute paths of the files, and so must run on the same machine as fantoccini.
+    ///
+    /// Selecting more than one file requires the element to have the `multiple` attribute.
+    pub async fn upload_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<(), error::CmdError> {
+        let is_file_input = self.tag_name().await?.eq_ignore_ascii_case("input")
+            && self
+                .attr("type")
+                .await?
+                .map_or(false, |t| t.eq_ignore_ascii_case("file"));
+        if !is_file_input {
+            return Err(error::CmdError::InvalidArgument(
+                "element".to_string(),
+                "files can only be uploaded through an <input type=\"file\"> element".to_string(),
+            ));
+        }
+        if paths.is_empty() {
+            return Err(error::CmdError::InvalidArgument(
+                "paths".to_string(),
+                "no files to upload".to_string(),
+            ));
+        }
+        if paths.len() > 1 && self.attr("multiple").await?.is_none() {
+            return Err(error::CmdError::InvalidArgument(
+                "paths".to_string(),
+                "only one file can be uploaded through an <input type=\"file\"> element \
+                 without the multiple attribute"
+                    .to_string(),
+            ));
+        }
+
+        #[cfg(feature = "remote-upload")]
+        let mut remote = self
+            .client
+            .capabilities()
+            .map_or(false, |caps| caps.keys().any(|k| k.starts_with("se:")));
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let path = path.as_ref();
+            let unreadable = |e: io::Error| {
+                error::CmdError::InvalidArgument(
+                    "paths".to_string(),
+                    format!("{}: {}", path.display(), e),
+                )
+            };
+            let path = fs::canonicalize(path).map_err(unreadable)?;
+            #[cfg(feature = "remote-upload")]
+            if remote {
+                let file = crate::upload::zip_file(&path).map_err(unreadable)?;
+                match self
+                    .client
+                    .issue_cmd(crate::upload::UploadFile { file })
+                    .await
+                {
+                    Ok(Json::String(uploaded)) => {
+                        files.push(uploaded);
+                        continue;
+                    }
+                    Ok(v) => return Err(error::CmdError::NotW3C(v)),
+                    // not a Selenium server after all, so the files must be local
+                    Err(e) if e.is_unknown_command() && files.is_empty() => remote = false,
+                    Err(e) => return Err(e),
+                }
+            }
+            files.push(path.to_string_lossy().into_owned());
+        }
+        self.send_keys(&files.join("\n")).await
+    }
 }
 
 /// [Screen Capture](https://www.w3.org/TR/webdriver1/#screen-capture)
@@ -605,12 +671,13 @@ impl Form {
 impl Form {
     /// Find a form input using the given `locator` and set its value to `value`.
     pub async fn set(&self, locator: Locator<'_>, value: &str) -> Result<Self, error::CmdError> {
-        let locator =
-            WebDriverCommand::FindElementElement(self.form.clone(), locator.into_parameters());
+        let field = self
+            .client
+            .locate(SearchRoot::Element(&self.form), locator)
+            .await?
+            .element;
         let value = Json::from(value);
 
-        let res = self.client.issue(locator).await?;
-        let field = self.client.parse_lookup(res)?;
         let args = vec![via_json!(&field), value];
         let cmd = webdriver::command::JavascriptCommandParameters {
             script: "arguments[0].value = arguments[1]".to_string(),
@@ -652,10 +719,11 @@ impl Form {
     ///
     /// `false` is returned if a matching button was not found.
     pub async fn submit_with(&self, button: Locator<'_>) -> Result<(), error::CmdError> {
-        let locator =
-            WebDriverCommand::FindElementElement(self.form.clone(), button.into_parameters());
-        let res = self.client.issue(locator).await?;
-        let submit = self.client.parse_lookup(res)?;
+        let submit = self
+            .client
+            .locate(SearchRoot::Element(&self.form), button)
+            .await?
+            .element;
         let res = self
             .client
             .issue(WebDriverCommand::ElementClick(submit))
@@ -7,6 +7,7 @@ use std::error::Error;
 use std::fmt;
 use std::io::Error as IOError;
 use std::str::FromStr;
+use std::time::Duration;
 use url::ParseError;
 
 /// An error occurred while attempting to establish a session for a new `Client`.
@@ -128,6 +129,17 @@ pub enum CmdError {
     /// consuming methods, waiting on some condition, may return this error, indicating that the
     /// timeout waiting for the condition occurred.
     WaitTimeout,
+
+    /// The WebDriver server did not respond to a request within the request timeout.
+    ///
+    /// See [`ClientBuilder::request_timeout`](crate::ClientBuilder::request_timeout) and
+    /// [`Client::with_timeout`](crate::Client::with_timeout). Unlike
+    /// [`WaitTimeout`](CmdError::WaitTimeout), this means that a single command took too long.
+    RequestTimeout(Duration),
+
+    /// A [`Replayer`](crate::cassette::Replayer) has no recorded interaction left that matches
+    /// the request, given here as its method and path.
+    NotRecorded(String),
 }
 
 macro_rules! is_helper {
@@ -196,6 +208,8 @@ impl Error for CmdError {
             CmdError::ImageDecodeError(..) => "error decoding image",
             CmdError::PdfDecodeError(..) => "error decoding PDF",
             CmdError::WaitTimeout => "timeout waiting on condition",
+            CmdError::RequestTimeout(..) => "webdriver request timed out",
+            CmdError::NotRecorded(..) => "no recorded interaction matches request",
         }
     }
 
@@ -211,7 +225,9 @@ impl Error for CmdError {
             CmdError::NotJson(_)
             | CmdError::NotW3C(_)
             | CmdError::InvalidArgument(..)
-            | CmdError::WaitTimeout => None,
+            | CmdError::WaitTimeout
+            | CmdError::RequestTimeout(..)
+            | CmdError::NotRecorded(..) => None,
         }
     }
 }
@@ -236,6 +252,8 @@ impl fmt::Display for CmdError {
                 write!(f, "Invalid argument `{}`: {}", arg, msg)
             }
             CmdError::WaitTimeout => Ok(()),
+            CmdError::RequestTimeout(ref t) => write!(f, "no response within {:?}", t),
+            CmdError::NotRecorded(ref request) => write!(f, "{}", request),
         }
     }
 }
@@ -0,0 +1,228 @@
+//! Firefox-specific commands provided by geckodriver.
+//!
+//! [geckodriver](https://github.com/mozilla/geckodriver) has a handful of endpoints beyond the
+//! WebDriver standard. Each of them is available as a [`WebDriverCompatibleCommand`] that can be
+//! sent with [`Client::issue_cmd`], and through a method on [`Client`]:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), fantoccini::error::CmdError> {
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let c = fantoccini::ClientBuilder::native().connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = fantoccini::ClientBuilder::rustls().expect("rustls initialization").connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! use fantoccini::firefox::Addon;
+//!
+//! let id = c
+//!     .install_addon(Addon::Path("/tmp/ublock_origin.xpi".into()), true)
+//!     .await?;
+//! c.goto("https://www.wikipedia.org/").await?;
+//! let png = c.full_screenshot().await?;
+//! c.uninstall_addon(&id).await?;
+//! # c.close().await
+//! # }
+//! ```
+use crate::error;
+use crate::wd::WebDriverCompatibleCommand;
+use crate::Client;
+use base64::Engine;
+use http::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use std::path::PathBuf;
+use url::{ParseError, Url};
+
+/// The URL of the geckodriver endpoint `path` for the given session.
+fn moz_endpoint(base_url: &Url, session_id: Option<&str>, path: &str) -> Result<Url, ParseError> {
+    base_url.join(&format!(
+        "session/{}/moz/{}",
+        session_id.unwrap_or_default(),
+        path
+    ))
+}
+
+/// An add-on to install with [`InstallAddon`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Addon {
+    /// The `.xpi` file at the given path on the machine that geckodriver runs on.
+    Path(PathBuf),
+    /// The contents of an `.xpi` file.
+    Bytes(Vec<u8>),
+}
+
+/// Install a Firefox add-on.
+///
+/// The response is the add-on's ID. See [`Client::install_addon`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstallAddon {
+    /// The add-on to install.
+    pub addon: Addon,
+    /// Whether to install the add-on only until Firefox is closed.
+    ///
+    /// Only temporary installs may be of add-ons that are not signed by Mozilla.
+    pub temporary: bool,
+}
+
+impl WebDriverCompatibleCommand for InstallAddon {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        moz_endpoint(base_url, session_id, "addon/install")
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        let body = match self.addon {
+            Addon::Path(ref path) => json!({
+                "path": path.to_string_lossy(),
+                "temporary": self.temporary,
+            }),
+            Addon::Bytes(ref xpi) => json!({
+                "addon": base64::engine::general_purpose::STANDARD.encode(xpi),
+                "temporary": self.temporary,
+            }),
+        };
+        (Method::POST, Some(body.to_string()))
+    }
+}
+
+/// Uninstall the Firefox add-on with the given ID.
+///
+/// See [`Client::uninstall_addon`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UninstallAddon {
+    /// The ID of the add-on, as returned by [`InstallAddon`].
+    pub id: String,
+}
+
+impl WebDriverCompatibleCommand for UninstallAddon {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        moz_endpoint(base_url, session_id, "addon/uninstall")
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        (Method::POST, Some(json!({ "id": self.id }).to_string()))
+    }
+}
+
+/// Take a screenshot of the whole page, rather than just the part in the viewport.
+///
+/// The response is a base64-encoded PNG. See [`Client::full_screenshot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FullScreenshot;
+
+impl WebDriverCompatibleCommand for FullScreenshot {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        moz_endpoint(base_url, session_id, "screenshot/full")
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        (Method::GET, None)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+/// What commands in a Firefox session operate on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Context {
+    /// The web page, which is the default.
+    Content,
+    /// Firefox's own user interface, which requires the `MOZ_REMOTE_ALLOW_SYSTEM_ACCESS`
+    /// environment variable to be set for Firefox.
+    Chrome,
+}
+
+/// Get the [`Context`] that commands operate on.
+///
+/// See [`Client::firefox_context`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GetContext;
+
+impl WebDriverCompatibleCommand for GetContext {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        moz_endpoint(base_url, session_id, "context")
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        (Method::GET, None)
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+}
+
+/// Switch the [`Context`] that commands operate on.
+///
+/// See [`Client::set_firefox_context`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetContext(pub Context);
+
+impl WebDriverCompatibleCommand for SetContext {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        moz_endpoint(base_url, session_id, "context")
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        (Method::POST, Some(json!({ "context": self.0 }).to_string()))
+    }
+
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
+
+/// [Firefox-specific commands](crate::firefox)
+impl Client {
+    /// Install a Firefox add-on, and return its ID.
+    ///
+    /// If `temporary` is `true`, the add-on is only installed until Firefox is closed, and need
+    /// not be signed by Mozilla.
+    pub async fn install_addon(
+        &self,
+        addon: Addon,
+        temporary: bool,
+    ) -> Result<String, error::CmdError> {
+        match self.issue_cmd(InstallAddon { addon, temporary }).await? {
+            Json::String(id) => Ok(id),
+            v => Err(error::CmdError::NotW3C(v)),
+        }
+    }
+
+    /// Uninstall the Firefox add-on with the given ID.
+    pub async fn uninstall_addon(&self, id: &str) -> Result<(), error::CmdError> {
+        self.issue_cmd(UninstallAddon { id: id.to_string() })
+            .await
+            .map(|_| ())
+    }
+
+    /// Get a PNG-encoded screenshot of the whole page, including the parts that are scrolled out
+    /// of view.
+    ///
+    /// This is only supported by Firefox. See [`Client::screenshot`] for a screenshot of just the
+    /// viewport.
+    pub async fn full_screenshot(&self) -> Result<Vec<u8>, error::CmdError> {
+        let src = self.issue_cmd(FullScreenshot).await?;
+        if let Some(src) = src.as_str() {
+            base64::engine::general_purpose::STANDARD
+                .decode(src)
+                .map_err(error::CmdError::ImageDecodeError)
+        } else {
+            Err(error::CmdError::NotW3C(src))
+        }
+    }
+
+    /// Get the [`Context`] that commands in this Firefox session operate on.
+    pub async fn firefox_context(&self) -> Result<Context, error::CmdError> {
+        let context = self.issue_cmd(GetContext).await?;
+        serde_json::from_value(context.clone()).map_err(|_| error::CmdError::NotW3C(context))
+    }
+
+    /// Switch between operating on the web page and on Firefox's own user interface.
+    pub async fn set_firefox_context(&self, context: Context) -> Result<(), error::CmdError> {
+        self.issue_cmd(SetContext(context)).await.map(|_| ())
+    }
+}
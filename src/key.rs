@@ -209,7 +209,7 @@ impl Add<&Key> for &str {
     type Output = String;
 
     fn add(self, rhs: &Key) -> Self::Output {
-        String::new() + &self + rhs
+        String::new() + self + rhs
     }
 }
 
@@ -229,6 +229,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_to_owned)]
     fn test_key_string() {
         assert_eq!(Key::Control + &"a".to_string(), "\u{e009}a".to_string());
         assert_eq!("a".to_string() + &Key::Control, "a\u{e009}".to_string());
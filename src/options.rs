@@ -0,0 +1,758 @@
+//! Typed builders for browser-specific capabilities.
+//!
+//! Each browser takes its options as a JSON object under a vendor-prefixed capability, such as
+//! [`moz:firefoxOptions`][1] or [`goog:chromeOptions`][2]. The builders in this module produce
+//! those objects, and merge them into a set of [`Capabilities`] that can be passed to
+//! [`ClientBuilder::capabilities`](crate::ClientBuilder::capabilities):
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::options::{BrowserOptions, ChromeOptions, MobileEmulation};
+//! use fantoccini::ClientBuilder;
+//!
+//! let caps = ChromeOptions::new()
+//!     .headless()
+//!     .arg("--disable-gpu")
+//!     .mobile_emulation(MobileEmulation::Device("Pixel 7".to_string()))
+//!     .to_capabilities();
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let mut builder = ClientBuilder::native();
+//! # #[cfg(feature = "rustls-tls")]
+//! # let mut builder = ClientBuilder::rustls()?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let mut builder: ClientBuilder<hyper_util::client::legacy::connect::HttpConnector> = unreachable!();
+//! let c = builder.capabilities(caps).connect("http://localhost:9515").await?;
+//! # c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [1]: https://developer.mozilla.org/en-US/docs/Web/WebDriver/Capabilities/firefoxOptions
+//! [2]: https://developer.chrome.com/docs/chromedriver/capabilities
+use crate::wd::Capabilities;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as Json};
+use std::fmt;
+#[cfg(feature = "firefox-profile")]
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for a particular browser that can be merged into a set of [`Capabilities`].
+pub trait BrowserOptions {
+    /// Merge these options into `caps`.
+    ///
+    /// This sets `browserName`, and adds these options to the browser's vendor-prefixed
+    /// capability, keeping any options already there that these options do not set.
+    /// Arguments, extensions, preferences, and environment variables are added to those already
+    /// present.
+    fn merge_into(&self, caps: &mut Capabilities);
+
+    /// Build a new set of capabilities from these options.
+    fn to_capabilities(&self) -> Capabilities {
+        let mut caps = Capabilities::new();
+        self.merge_into(&mut caps);
+        caps
+    }
+}
+
+/// When a navigation command such as [`Client::goto`](crate::Client::goto) returns.
+///
+/// See [the specification][1] for details. Fantoccini defaults to [`Normal`](Self::Normal).
+///
+/// [1]: https://www.w3.org/TR/webdriver/#dfn-page-loading-strategy
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageLoadStrategy {
+    /// Return as soon as the navigation has started.
+    None,
+    /// Return once the document has been parsed, but before all resources have loaded.
+    Eager,
+    /// Return once the page and all its resources have loaded.
+    Normal,
+}
+
+impl fmt::Display for PageLoadStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Eager => write!(f, "eager"),
+            Self::Normal => write!(f, "normal"),
+        }
+    }
+}
+
+/// How the browser connects to the network, as given in the [`proxy`][1] capability.
+///
+/// Pass this to [`ClientBuilder::proxy`](crate::ClientBuilder::proxy) to have the browser, as well
+/// as [raw requests](crate::Client::raw_client_for), go through a proxy.
+///
+/// [1]: https://www.w3.org/TR/webdriver/#proxy
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(into = "WireProxy", try_from = "WireProxy")]
+pub enum ProxyConfig {
+    /// Connect directly, without a proxy.
+    Direct,
+    /// Use the operating system's proxy settings.
+    System,
+    /// Detect the proxy with the [Web Proxy Auto-Discovery Protocol][1].
+    ///
+    /// [1]: https://en.wikipedia.org/wiki/Web_Proxy_Auto-Discovery_Protocol
+    Autodetect,
+    /// Use the [proxy auto-config file][1] at the given URL.
+    ///
+    /// [1]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Proxy_servers_and_tunneling/Proxy_Auto-Configuration_PAC_file
+    Pac(String),
+    /// Use the given proxies.
+    ///
+    /// Proxies are given as `host:port`. Traffic that has no proxy set for it goes through the
+    /// SOCKS proxy if there is one, and is sent directly otherwise.
+    Manual {
+        /// The proxy for HTTP traffic.
+        http: Option<String>,
+        /// The proxy for HTTPS traffic.
+        ssl: Option<String>,
+        /// A SOCKS proxy.
+        socks: Option<String>,
+        /// The version of the SOCKS protocol that `socks` speaks, either 4 or 5.
+        socks_version: Option<u8>,
+        /// Hosts that are connected to directly, such as `"localhost"` or `".example.com"`.
+        no_proxy: Vec<String>,
+    },
+}
+
+impl ProxyConfig {
+    /// Send both HTTP and HTTPS traffic through the proxy at `host:port`.
+    ///
+    /// This is what you want for intercepting proxies such as `mitmproxy`.
+    pub fn manual(proxy: impl Into<String>) -> Self {
+        let proxy = proxy.into();
+        ProxyConfig::Manual {
+            http: Some(proxy.clone()),
+            ssl: Some(proxy),
+            socks: None,
+            socks_version: None,
+            no_proxy: Vec::new(),
+        }
+    }
+}
+
+/// The `proxy` capability as it appears on the wire.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireProxy {
+    proxy_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy_autoconfig_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ssl_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socks_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socks_version: Option<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    no_proxy: Vec<String>,
+}
+
+impl From<ProxyConfig> for WireProxy {
+    fn from(proxy: ProxyConfig) -> Self {
+        let kind = |kind: &str| WireProxy {
+            proxy_type: kind.to_string(),
+            ..Default::default()
+        };
+        match proxy {
+            ProxyConfig::Direct => kind("direct"),
+            ProxyConfig::System => kind("system"),
+            ProxyConfig::Autodetect => kind("autodetect"),
+            ProxyConfig::Pac(url) => WireProxy {
+                proxy_autoconfig_url: Some(url),
+                ..kind("pac")
+            },
+            ProxyConfig::Manual {
+                http,
+                ssl,
+                socks,
+                socks_version,
+                no_proxy,
+            } => WireProxy {
+                http_proxy: http,
+                ssl_proxy: ssl,
+                socks_proxy: socks,
+                socks_version,
+                no_proxy,
+                ..kind("manual")
+            },
+        }
+    }
+}
+
+impl TryFrom<WireProxy> for ProxyConfig {
+    type Error = String;
+
+    fn try_from(proxy: WireProxy) -> Result<Self, Self::Error> {
+        match &*proxy.proxy_type {
+            "direct" => Ok(ProxyConfig::Direct),
+            "system" => Ok(ProxyConfig::System),
+            "autodetect" => Ok(ProxyConfig::Autodetect),
+            "pac" => proxy
+                .proxy_autoconfig_url
+                .map(ProxyConfig::Pac)
+                .ok_or_else(|| "pac proxy without proxyAutoconfigUrl".to_string()),
+            "manual" => Ok(ProxyConfig::Manual {
+                http: proxy.http_proxy,
+                ssl: proxy.ssl_proxy,
+                socks: proxy.socks_proxy,
+                socks_version: proxy.socks_version,
+                no_proxy: proxy.no_proxy,
+            }),
+            other => Err(format!("unknown proxyType {:?}", other)),
+        }
+    }
+}
+
+/// Get the object under `key` in `caps`, replacing whatever is there if it is not an object.
+fn vendor_object<'a>(caps: &'a mut Capabilities, key: &str) -> &'a mut Map<String, Json> {
+    let value = caps
+        .entry(key.to_string())
+        .or_insert_with(|| Json::Object(Map::new()));
+    if !value.is_object() {
+        *value = Json::Object(Map::new());
+    }
+    value.as_object_mut().expect("just made it an object")
+}
+
+/// Append `items` to the array under `key` in `object`.
+fn extend_array(object: &mut Map<String, Json>, key: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    let value = object.entry(key.to_string()).or_insert_with(|| json!([]));
+    if !value.is_array() {
+        *value = json!([]);
+    }
+    let array = value.as_array_mut().expect("just made it an array");
+    array.extend(items.iter().cloned().map(Json::String));
+}
+
+/// Add the entries of `items` to the object under `key` in `object`.
+fn extend_object(object: &mut Map<String, Json>, key: &str, items: &Map<String, Json>) {
+    if items.is_empty() {
+        return;
+    }
+    let value = object.entry(key.to_string()).or_insert_with(|| json!({}));
+    if !value.is_object() {
+        *value = json!({});
+    }
+    let map = value.as_object_mut().expect("just made it an object");
+    map.extend(items.clone());
+}
+
+fn path_to_json(path: &Path) -> Json {
+    Json::String(path.to_string_lossy().into_owned())
+}
+
+/// The verbosity of geckodriver and Firefox's logging.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FirefoxLogLevel {
+    /// Log everything.
+    Trace,
+    /// Log debugging information and up.
+    Debug,
+    /// Log configuration information and up.
+    Config,
+    /// Log informational messages and up.
+    Info,
+    /// Log warnings and errors.
+    Warn,
+    /// Log errors only.
+    Error,
+    /// Log fatal errors only.
+    Fatal,
+}
+
+impl fmt::Display for FirefoxLogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trace => write!(f, "trace"),
+            Self::Debug => write!(f, "debug"),
+            Self::Config => write!(f, "config"),
+            Self::Info => write!(f, "info"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+            Self::Fatal => write!(f, "fatal"),
+        }
+    }
+}
+
+/// The minimum severity of the messages a Chromium-based browser logs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChromiumLogLevel {
+    /// Log informational messages and up.
+    Info,
+    /// Log warnings and errors.
+    Warning,
+    /// Log errors only.
+    Error,
+    /// Log fatal errors only.
+    Fatal,
+}
+
+impl ChromiumLogLevel {
+    /// The value of the browser's `--log-level` switch for this level.
+    fn switch(self) -> u8 {
+        match self {
+            Self::Info => 0,
+            Self::Warning => 1,
+            Self::Error => 2,
+            Self::Fatal => 3,
+        }
+    }
+}
+
+/// Options for Firefox, passed to geckodriver as [`moz:firefoxOptions`][1].
+///
+/// [1]: https://developer.mozilla.org/en-US/docs/Web/WebDriver/Capabilities/firefoxOptions
+#[derive(Clone, Debug, Default)]
+pub struct FirefoxOptions {
+    binary: Option<PathBuf>,
+    args: Vec<String>,
+    prefs: Map<String, Json>,
+    env: Map<String, Json>,
+    log: Option<FirefoxLogLevel>,
+    profile: Option<String>,
+    page_load_strategy: Option<PageLoadStrategy>,
+}
+
+impl FirefoxOptions {
+    /// Start with no options set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the Firefox binary at `path` rather than the one geckodriver finds by default.
+    pub fn binary(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.binary = Some(path.into());
+        self
+    }
+
+    /// Pass an additional command-line argument to Firefox.
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Run Firefox without a visible window.
+    pub fn headless(&mut self) -> &mut Self {
+        self.arg("--headless")
+    }
+
+    /// Set the `about:config` preference `name` to `value`.
+    pub fn pref(&mut self, name: impl Into<String>, value: impl Into<Json>) -> &mut Self {
+        self.prefs.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set an environment variable for the Firefox process.
+    pub fn env(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.env.insert(name.into(), Json::String(value.into()));
+        self
+    }
+
+    /// Set the verbosity of geckodriver and Firefox's logging.
+    pub fn log_level(&mut self, level: FirefoxLogLevel) -> &mut Self {
+        self.log = Some(level);
+        self
+    }
+
+    /// Start Firefox with a copy of the given profile.
+    ///
+    /// The profile is packaged right away, which fails if any of the files it refers to cannot
+    /// be read.
+    #[cfg(feature = "firefox-profile")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "firefox-profile")))]
+    pub fn profile(&mut self, profile: &crate::profile::FirefoxProfile) -> io::Result<&mut Self> {
+        self.profile = Some(profile.to_base64()?);
+        Ok(self)
+    }
+
+    /// Set when navigation commands return.
+    pub fn page_load_strategy(&mut self, strategy: PageLoadStrategy) -> &mut Self {
+        self.page_load_strategy = Some(strategy);
+        self
+    }
+}
+
+impl BrowserOptions for FirefoxOptions {
+    fn merge_into(&self, caps: &mut Capabilities) {
+        caps.insert("browserName".to_string(), json!("firefox"));
+        if let Some(strategy) = self.page_load_strategy {
+            caps.insert("pageLoadStrategy".to_string(), json!(strategy.to_string()));
+        }
+
+        let opts = vendor_object(caps, "moz:firefoxOptions");
+        if let Some(ref binary) = self.binary {
+            opts.insert("binary".to_string(), path_to_json(binary));
+        }
+        extend_array(opts, "args", &self.args);
+        extend_object(opts, "prefs", &self.prefs);
+        extend_object(opts, "env", &self.env);
+        if let Some(level) = self.log {
+            opts.insert("log".to_string(), json!({ "level": level.to_string() }));
+        }
+        if let Some(ref profile) = self.profile {
+            opts.insert("profile".to_string(), json!(profile));
+        }
+    }
+}
+
+impl From<FirefoxOptions> for Capabilities {
+    fn from(options: FirefoxOptions) -> Self {
+        options.to_capabilities()
+    }
+}
+
+/// How a Chromium-based browser should emulate a mobile device.
+///
+/// See [the ChromeDriver documentation][1] for details.
+///
+/// [1]: https://developer.chrome.com/docs/chromedriver/mobile-emulation
+#[derive(Clone, Debug, PartialEq)]
+pub enum MobileEmulation {
+    /// Emulate a device from the browser's list of devices, such as `"Pixel 7"`.
+    Device(String),
+    /// Emulate a device with the given screen.
+    Metrics {
+        /// The width of the screen, in CSS pixels.
+        width: u32,
+        /// The height of the screen, in CSS pixels.
+        height: u32,
+        /// The number of device pixels per CSS pixel.
+        pixel_ratio: f64,
+        /// Whether to emulate touch events.
+        touch: bool,
+        /// The user agent to report, if not the browser's own.
+        user_agent: Option<String>,
+    },
+}
+
+impl MobileEmulation {
+    fn to_json(&self) -> Json {
+        match self {
+            Self::Device(name) => json!({ "deviceName": name }),
+            Self::Metrics {
+                width,
+                height,
+                pixel_ratio,
+                touch,
+                user_agent,
+            } => {
+                let mut emulation = json!({
+                    "deviceMetrics": {
+                        "width": width,
+                        "height": height,
+                        "pixelRatio": pixel_ratio,
+                        "touch": touch,
+                    }
+                });
+                if let Some(ua) = user_agent {
+                    emulation["userAgent"] = json!(ua);
+                }
+                emulation
+            }
+        }
+    }
+}
+
+macro_rules! chromium_options {
+    ($(#[$doc:meta])* $name:ident, $browser:literal, $key:literal) => {
+        $(#[$doc])*
+        ///
+        /// The driver starts the browser with its own environment, and has no option to change
+        /// it, so there is no `env` here. Set environment variables for the driver process
+        /// instead, for example with `DriverService::env` from the `driver` feature.
+        #[derive(Clone, Debug, Default)]
+        pub struct $name {
+            binary: Option<PathBuf>,
+            args: Vec<String>,
+            log: Option<ChromiumLogLevel>,
+            prefs: Map<String, Json>,
+            extensions: Vec<String>,
+            mobile_emulation: Option<MobileEmulation>,
+            debugger_address: Option<String>,
+            page_load_strategy: Option<PageLoadStrategy>,
+        }
+
+        impl $name {
+            /// Start with no options set.
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Use the browser binary at `path` rather than the one the driver finds by default.
+            pub fn binary(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+                self.binary = Some(path.into());
+                self
+            }
+
+            /// Pass an additional command-line argument to the browser.
+            pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+                self.args.push(arg.into());
+                self
+            }
+
+            /// Run the browser without a visible window.
+            pub fn headless(&mut self) -> &mut Self {
+                self.arg("--headless")
+            }
+
+            /// Set the minimum severity of the messages the browser logs, using its `--log-level`
+            /// switch.
+            pub fn log_level(&mut self, level: ChromiumLogLevel) -> &mut Self {
+                self.log = Some(level);
+                self
+            }
+
+            /// Set the user preference `name` to `value`.
+            ///
+            /// Nested preferences are named with dots, as in `"download.default_directory"`.
+            pub fn pref(&mut self, name: impl Into<String>, value: impl Into<Json>) -> &mut Self {
+                self.prefs.insert(name.into(), value.into());
+                self
+            }
+
+            /// Install the packed (`.crx`) extension with the given contents.
+            pub fn extension(&mut self, crx: &[u8]) -> &mut Self {
+                self.extensions
+                    .push(base64::engine::general_purpose::STANDARD.encode(crx));
+                self
+            }
+
+            /// Emulate a mobile device.
+            pub fn mobile_emulation(&mut self, emulation: MobileEmulation) -> &mut Self {
+                self.mobile_emulation = Some(emulation);
+                self
+            }
+
+            /// Connect to an already running browser at `address`, such as `"127.0.0.1:9222"`,
+            /// rather than starting a new one.
+            pub fn debugger_address(&mut self, address: impl Into<String>) -> &mut Self {
+                self.debugger_address = Some(address.into());
+                self
+            }
+
+            /// Set when navigation commands return.
+            pub fn page_load_strategy(&mut self, strategy: PageLoadStrategy) -> &mut Self {
+                self.page_load_strategy = Some(strategy);
+                self
+            }
+        }
+
+        impl BrowserOptions for $name {
+            fn merge_into(&self, caps: &mut Capabilities) {
+                caps.insert("browserName".to_string(), json!($browser));
+                if let Some(strategy) = self.page_load_strategy {
+                    caps.insert("pageLoadStrategy".to_string(), json!(strategy.to_string()));
+                }
+
+                let opts = vendor_object(caps, $key);
+                if let Some(ref binary) = self.binary {
+                    opts.insert("binary".to_string(), path_to_json(binary));
+                }
+                extend_array(opts, "args", &self.args);
+                if let Some(level) = self.log {
+                    extend_array(opts, "args", &[format!("--log-level={}", level.switch())]);
+                }
+                extend_object(opts, "prefs", &self.prefs);
+                extend_array(opts, "extensions", &self.extensions);
+                if let Some(ref emulation) = self.mobile_emulation {
+                    opts.insert("mobileEmulation".to_string(), emulation.to_json());
+                }
+                if let Some(ref address) = self.debugger_address {
+                    opts.insert("debuggerAddress".to_string(), json!(address));
+                }
+            }
+        }
+
+        impl From<$name> for Capabilities {
+            fn from(options: $name) -> Self {
+                options.to_capabilities()
+            }
+        }
+    };
+}
+
+chromium_options!(
+    /// Options for Chrome, passed to chromedriver as [`goog:chromeOptions`][1].
+    ///
+    /// Fantoccini always sets `goog:chromeOptions.w3c` when creating a session, so that
+    /// chromedriver speaks the standard protocol.
+    ///
+    /// [1]: https://developer.chrome.com/docs/chromedriver/capabilities#chromeoptions_object
+    ChromeOptions,
+    "chrome",
+    "goog:chromeOptions"
+);
+
+chromium_options!(
+    /// Options for Microsoft Edge, passed to msedgedriver as [`ms:edgeOptions`][1].
+    ///
+    /// [1]: https://learn.microsoft.com/en-us/microsoft-edge/webdriver-chromium/capabilities-edge-options
+    EdgeOptions,
+    "MicrosoftEdge",
+    "ms:edgeOptions"
+);
+
+/// Options for Safari, passed to safaridriver as [`safari:` capabilities][1].
+///
+/// [1]: https://developer.apple.com/documentation/webkit/about_webdriver_for_safari
+#[derive(Clone, Debug, Default)]
+pub struct SafariOptions {
+    technology_preview: bool,
+    automatic_inspection: Option<bool>,
+    automatic_profiling: Option<bool>,
+    page_load_strategy: Option<PageLoadStrategy>,
+}
+
+impl SafariOptions {
+    /// Start with no options set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use Safari Technology Preview rather than Safari.
+    pub fn technology_preview(&mut self) -> &mut Self {
+        self.technology_preview = true;
+        self
+    }
+
+    /// Open Web Inspector, and pause script execution, when the session starts.
+    pub fn automatic_inspection(&mut self, enabled: bool) -> &mut Self {
+        self.automatic_inspection = Some(enabled);
+        self
+    }
+
+    /// Start a Web Inspector timeline recording when the session starts.
+    pub fn automatic_profiling(&mut self, enabled: bool) -> &mut Self {
+        self.automatic_profiling = Some(enabled);
+        self
+    }
+
+    /// Set when navigation commands return.
+    pub fn page_load_strategy(&mut self, strategy: PageLoadStrategy) -> &mut Self {
+        self.page_load_strategy = Some(strategy);
+        self
+    }
+}
+
+impl BrowserOptions for SafariOptions {
+    fn merge_into(&self, caps: &mut Capabilities) {
+        let name = if self.technology_preview {
+            "Safari Technology Preview"
+        } else {
+            "safari"
+        };
+        caps.insert("browserName".to_string(), json!(name));
+        if let Some(strategy) = self.page_load_strategy {
+            caps.insert("pageLoadStrategy".to_string(), json!(strategy.to_string()));
+        }
+        if let Some(enabled) = self.automatic_inspection {
+            caps.insert("safari:automaticInspection".to_string(), json!(enabled));
+        }
+        if let Some(enabled) = self.automatic_profiling {
+            caps.insert("safari:automaticProfiling".to_string(), json!(enabled));
+        }
+    }
+}
+
+impl From<SafariOptions> for Capabilities {
+    fn from(options: SafariOptions) -> Self {
+        options.to_capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firefox() {
+        let caps = FirefoxOptions::new()
+            .binary("/opt/firefox/firefox")
+            .headless()
+            .pref("dom.webnotifications.enabled", false)
+            .env("MOZ_LOG", "nsHttp:5")
+            .log_level(FirefoxLogLevel::Trace)
+            .to_capabilities();
+        assert_eq!(
+            Json::Object(caps),
+            json!({
+                "browserName": "firefox",
+                "moz:firefoxOptions": {
+                    "binary": "/opt/firefox/firefox",
+                    "args": ["--headless"],
+                    "prefs": { "dom.webnotifications.enabled": false },
+                    "env": { "MOZ_LOG": "nsHttp:5" },
+                    "log": { "level": "trace" },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn merges_with_existing() {
+        let mut caps = Capabilities::new();
+        caps.insert(
+            "goog:chromeOptions".to_string(),
+            json!({ "w3c": true, "args": ["--disable-gpu"] }),
+        );
+        caps.insert("acceptInsecureCerts".to_string(), json!(true));
+
+        EdgeOptions::new()
+            .arg("--inprivate")
+            .log_level(ChromiumLogLevel::Warning)
+            .mobile_emulation(MobileEmulation::Metrics {
+                width: 360,
+                height: 640,
+                pixel_ratio: 3.0,
+                touch: true,
+                user_agent: None,
+            })
+            .merge_into(&mut caps);
+        ChromeOptions::new()
+            .headless()
+            .extension(b"crx")
+            .debugger_address("127.0.0.1:9222")
+            .page_load_strategy(PageLoadStrategy::Eager)
+            .merge_into(&mut caps);
+
+        assert_eq!(
+            Json::Object(caps),
+            json!({
+                "acceptInsecureCerts": true,
+                "browserName": "chrome",
+                "pageLoadStrategy": "eager",
+                "goog:chromeOptions": {
+                    "w3c": true,
+                    "args": ["--disable-gpu", "--headless"],
+                    "extensions": ["Y3J4"],
+                    "debuggerAddress": "127.0.0.1:9222",
+                },
+                "ms:edgeOptions": {
+                    "args": ["--inprivate", "--log-level=1"],
+                    "mobileEmulation": {
+                        "deviceMetrics": {
+                            "width": 360,
+                            "height": 640,
+                            "pixelRatio": 3.0,
+                            "touch": true,
+                        },
+                    },
+                },
+            })
+        );
+    }
+}
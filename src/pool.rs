@@ -0,0 +1,244 @@
+//! A pool of WebDriver sessions that are leased out to tasks one at a time.
+//!
+//! Starting a browser session is slow, so programs that keep several browsers busy usually want
+//! to reuse sessions rather than create a new one for every job. A [`SessionPool`] creates
+//! sessions lazily, up to a fixed number, and leases them out as [`PooledClient`]s. When a lease
+//! is dropped, the session is reset to a clean state and returned to the pool.
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! use fantoccini::pool::SessionPool;
+//! use fantoccini::ClientBuilder;
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let builder = ClientBuilder::native();
+//! # #[cfg(feature = "rustls-tls")]
+//! # let builder = ClientBuilder::rustls()?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let builder: ClientBuilder<hyper_util::client::legacy::connect::HttpConnector> = unreachable!();
+//! let pool = SessionPool::new(builder, "http://localhost:4444", 4);
+//!
+//! let mut jobs = Vec::new();
+//! for page in ["https://www.wikipedia.org/", "https://www.rust-lang.org/"] {
+//!     let pool = pool.clone();
+//!     jobs.push(tokio::spawn(async move {
+//!         let c = pool.get().await.expect("failed to create session");
+//!         c.goto(page).await?;
+//!         Ok::<_, fantoccini::error::CmdError>(c.title().await?)
+//!     }));
+//! }
+//! for job in jobs {
+//!     println!("{}", job.await??);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::wd::{TimeoutConfiguration, WindowHandle};
+use crate::{error, Client, ClientBuilder};
+use hyper_util::client::legacy::connect;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A pool of up to a fixed number of WebDriver sessions.
+///
+/// Cloning a `SessionPool` gives another handle to the same pool. See the
+/// [module-level documentation](self) for an example.
+#[derive(Debug)]
+pub struct SessionPool<C>
+where
+    C: connect::Connect + Send + Sync + Clone + Unpin,
+{
+    builder: Arc<ClientBuilder<C>>,
+    webdriver: Arc<str>,
+    shared: Arc<Shared>,
+}
+
+impl<C> Clone for SessionPool<C>
+where
+    C: connect::Connect + Send + Sync + Clone + Unpin,
+{
+    fn clone(&self) -> Self {
+        SessionPool {
+            builder: Arc::clone(&self.builder),
+            webdriver: Arc::clone(&self.webdriver),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+/// The parts of the pool that leases need to return their session.
+#[derive(Debug)]
+struct Shared {
+    idle: Mutex<Vec<Idle>>,
+    permits: Arc<Semaphore>,
+}
+
+/// A session along with the state to restore when it is returned to the pool.
+#[derive(Debug)]
+struct Idle {
+    client: Client,
+    window: WindowHandle,
+    timeouts: TimeoutConfiguration,
+}
+
+impl Idle {
+    /// Put the session back into the state it was in when it was created.
+    async fn reset(&mut self) -> Result<(), error::CmdError> {
+        let c = &self.client;
+        c.delete_all_cookies().await?;
+
+        let windows = c.windows().await?;
+        if !windows.contains(&self.window) {
+            // the original window was closed, so keep one of the others instead
+            if let Some(window) = windows.first() {
+                self.window = window.clone();
+            }
+        }
+        for window in windows {
+            if window != self.window {
+                c.switch_to_window(window).await?;
+                c.close_window().await?;
+            }
+        }
+        c.switch_to_window(self.window.clone()).await?;
+
+        c.goto("about:blank").await?;
+        c.update_timeouts(self.timeouts.clone()).await
+    }
+
+    /// End the session, ignoring any errors since it is going away anyway.
+    async fn discard(self) {
+        let _ = self.client.close().await;
+    }
+}
+
+impl<C> SessionPool<C>
+where
+    C: connect::Connect + Send + Sync + Clone + Unpin + 'static,
+{
+    /// Create a pool of up to `size` sessions with the WebDriver server at `webdriver`.
+    ///
+    /// Sessions are created with `builder` as they are needed, so creating the pool does not
+    /// connect to the server.
+    pub fn new(builder: ClientBuilder<C>, webdriver: &str, size: usize) -> Self {
+        SessionPool {
+            builder: Arc::new(builder),
+            webdriver: Arc::from(webdriver),
+            shared: Arc::new(Shared {
+                idle: Mutex::new(Vec::new()),
+                permits: Arc::new(Semaphore::new(size)),
+            }),
+        }
+    }
+
+    /// Lease a session from the pool.
+    ///
+    /// If all sessions are leased out, this waits until one is returned. Idle sessions are checked
+    /// with [`Client::title`] before they are handed out, and any session for which that fails,
+    /// for example because it has been lost, its window was closed, or an alert is open, is
+    /// closed and replaced with a new one.
+    pub async fn get(&self) -> Result<PooledClient, error::NewSessionError> {
+        let permit = Arc::clone(&self.shared.permits)
+            .acquire_owned()
+            .await
+            .expect("pool semaphore is never closed");
+
+        loop {
+            let idle = self.shared.idle.lock().unwrap().pop();
+            let Some(idle) = idle else {
+                break;
+            };
+            // whatever went wrong, a session that can't tell us its title is no use to anyone
+            match idle.client.title().await {
+                Ok(_) => return Ok(self.lease(idle, permit)),
+                Err(_) => idle.discard().await,
+            }
+        }
+
+        let client = self.builder.connect(&self.webdriver).await?;
+        let baseline = async {
+            let window = client.window().await?;
+            let timeouts = client.get_timeouts().await?;
+            Ok((window, timeouts))
+        };
+        match baseline.await {
+            Ok((window, timeouts)) => Ok(self.lease(
+                Idle {
+                    client,
+                    window,
+                    timeouts,
+                },
+                permit,
+            )),
+            Err(e) => {
+                let _ = client.close().await;
+                Err(error::NewSessionError::UnexpectedError(e))
+            }
+        }
+    }
+
+    fn lease(&self, idle: Idle, permit: OwnedSemaphorePermit) -> PooledClient {
+        PooledClient {
+            lease: Some((idle, permit)),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// The number of sessions that are currently idle in the pool.
+    pub fn idle(&self) -> usize {
+        self.shared.idle.lock().unwrap().len()
+    }
+}
+
+/// A [`Client`] leased from a [`SessionPool`].
+///
+/// When a `PooledClient` is dropped, its session is reset and then returned to the pool. Resetting
+/// deletes all cookies, closes all but the session's original window, navigates to `about:blank`,
+/// and restores the timeouts the session had when it was created. If any of that fails, the
+/// session is closed instead, and the pool creates a new session when one is next needed.
+///
+/// Resetting happens on a spawned task, so a `PooledClient` must be dropped within a tokio
+/// runtime for its session to be returned to the pool. Clones of the [`Client`] should not be
+/// kept around after the lease is dropped, as the session will then be handed to someone else.
+pub struct PooledClient {
+    lease: Option<(Idle, OwnedSemaphorePermit)>,
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for PooledClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PooledClient").field(&**self).finish()
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.lease.as_ref().expect("only taken on drop").0.client
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        let Some((mut idle, permit)) = self.lease.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            // without a runtime we cannot reset the session, so just let it go
+            return;
+        };
+        let shared = Arc::clone(&self.shared);
+        handle.spawn(async move {
+            match idle.reset().await {
+                Ok(()) => shared.idle.lock().unwrap().push(idle),
+                Err(_) => idle.discard().await,
+            }
+            // only let the next lease proceed once the session is back in the pool
+            drop(permit);
+        });
+    }
+}
@@ -0,0 +1,206 @@
+//! Packaging a Firefox profile for geckodriver.
+//!
+//! geckodriver can start Firefox with a custom profile, but it expects that profile to be sent
+//! along with the new session request as a base64-encoded zip archive in
+//! `moz:firefoxOptions.profile`. A [`FirefoxProfile`] builds such an archive, either from an
+//! existing profile directory or from scratch, and [`FirefoxOptions::profile`] adds it to the
+//! session's capabilities:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::options::{BrowserOptions, FirefoxOptions};
+//! use fantoccini::profile::FirefoxProfile;
+//! use fantoccini::ClientBuilder;
+//!
+//! let mut profile = FirefoxProfile::new();
+//! profile
+//!     .pref("browser.startup.homepage", "https://www.rust-lang.org/")
+//!     .extension("uBlock0@raymondhill.net", "/tmp/ublock_origin.xpi");
+//! let caps = FirefoxOptions::new()
+//!     .headless()
+//!     .profile(&profile)?
+//!     .to_capabilities();
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let mut builder = ClientBuilder::native();
+//! # #[cfg(feature = "rustls-tls")]
+//! # let mut builder = ClientBuilder::rustls()?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let mut builder: ClientBuilder<hyper_util::client::legacy::connect::HttpConnector> = unreachable!();
+//! let c = builder.capabilities(caps).connect("http://localhost:4444").await?;
+//! # c.close().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`FirefoxOptions::profile`]: crate::options::FirefoxOptions::profile
+use base64::Engine;
+use serde_json::Value as Json;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Files in a profile directory that only matter to a running Firefox, and are left out.
+const SKIPPED: &[&str] = &["lock", ".parentlock", "parent.lock"];
+
+/// The OID that marks a certificate override fingerprint as SHA-256.
+const SHA256_OID: &str = "OID.2.16.840.1.101.3.4.2.1";
+
+/// A Firefox profile to be sent to geckodriver.
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Clone, Debug, Default)]
+pub struct FirefoxProfile {
+    base: Option<PathBuf>,
+    prefs: Vec<(String, Json)>,
+    cert_overrides: Vec<String>,
+    extensions: Vec<(String, PathBuf)>,
+}
+
+impl FirefoxProfile {
+    /// Start from an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the existing profile in `dir`.
+    ///
+    /// The directory is only read when the profile is [packaged](Self::to_zip), so changes made
+    /// to it until then are included. Lock files left behind by a running Firefox are skipped.
+    pub fn from_dir(dir: impl Into<PathBuf>) -> Self {
+        FirefoxProfile {
+            base: Some(dir.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the `about:config` preference `name` to `value` in the profile's `user.js`.
+    ///
+    /// Preferences are added after any that are already in the `user.js` of the directory the
+    /// profile was created from, and so take precedence over them.
+    pub fn pref(&mut self, name: impl Into<String>, value: impl Into<Json>) -> &mut Self {
+        self.prefs.push((name.into(), value.into()));
+        self
+    }
+
+    /// Trust the certificate with the given SHA-256 fingerprint for `host` and `port`, even if it
+    /// would otherwise be rejected.
+    ///
+    /// The fingerprint is written as uppercase, colon-separated hex bytes, as shown by Firefox's
+    /// certificate viewer, such as `"AB:CD:…"`.
+    pub fn certificate_override(
+        &mut self,
+        host: &str,
+        port: u16,
+        sha256_fingerprint: &str,
+    ) -> &mut Self {
+        self.cert_overrides.push(format!(
+            "{}:{}:\t{}\t{}\t",
+            host,
+            port,
+            SHA256_OID,
+            sha256_fingerprint.to_ascii_uppercase()
+        ));
+        self
+    }
+
+    /// Install the extension packaged in the `.xpi` file at `xpi`.
+    ///
+    /// `id` must match the extension's ID, as given in `browser_specific_settings.gecko.id` in
+    /// its manifest, or Firefox will not load it.
+    pub fn extension(&mut self, id: impl Into<String>, xpi: impl Into<PathBuf>) -> &mut Self {
+        self.extensions.push((id.into(), xpi.into()));
+        self
+    }
+
+    /// Package the profile as a zip archive.
+    pub fn to_zip(&self) -> io::Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+
+        let mut user_js = String::new();
+        let mut cert_override = String::new();
+        if let Some(ref base) = self.base {
+            let mut files = Vec::new();
+            collect_files(base, Path::new(""), &mut files)?;
+            for relative in files {
+                let path = base.join(&relative);
+                let name = zip_name(&relative);
+                match &*name {
+                    // these are written below, along with our additions
+                    "user.js" => user_js = fs::read_to_string(path)?,
+                    "cert_override.txt" => cert_override = fs::read_to_string(path)?,
+                    _ => {
+                        zip.start_file(name, options)?;
+                        zip.write_all(&fs::read(path)?)?;
+                    }
+                }
+            }
+        }
+
+        for (name, value) in &self.prefs {
+            if !user_js.is_empty() && !user_js.ends_with('\n') {
+                user_js.push('\n');
+            }
+            user_js.push_str(&format!(
+                "user_pref({}, {});\n",
+                Json::String(name.clone()),
+                value
+            ));
+        }
+        if !user_js.is_empty() {
+            zip.start_file("user.js", options)?;
+            zip.write_all(user_js.as_bytes())?;
+        }
+
+        for line in &self.cert_overrides {
+            if !cert_override.is_empty() && !cert_override.ends_with('\n') {
+                cert_override.push('\n');
+            }
+            cert_override.push_str(line);
+            cert_override.push('\n');
+        }
+        if !cert_override.is_empty() {
+            zip.start_file("cert_override.txt", options)?;
+            zip.write_all(cert_override.as_bytes())?;
+        }
+
+        for (id, xpi) in &self.extensions {
+            zip.start_file(format!("extensions/{}.xpi", id), options)?;
+            zip.write_all(&fs::read(xpi)?)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+
+    /// Package the profile as a base64-encoded zip archive, as geckodriver expects it.
+    pub fn to_base64(&self) -> io::Result<String> {
+        Ok(base64::engine::general_purpose::STANDARD.encode(self.to_zip()?))
+    }
+}
+
+/// Collect the paths, relative to `root`, of all files under `root.join(dir)`.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let relative = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &relative, files)?;
+        } else if !SKIPPED.iter().any(|&skip| entry.file_name() == skip) {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// The name of the file at `relative` inside a zip archive, which always uses `/` as separator.
+fn zip_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,251 @@
+//! Sending raw requests through the same proxy as the browser.
+//!
+//! The connector that [`ClientBuilder`](crate::ClientBuilder) is given only knows how to reach
+//! a host directly, so raw requests for a session with a [`ProxyConfig`] go through a separate
+//! client instead. That client routes each connection according to the proxy configuration, and
+//! then layers TLS on top of it with whichever TLS implementation fantoccini was built with.
+use crate::options::ProxyConfig;
+use http::Uri;
+use http_body_util::combinators::BoxBody;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_util::client::legacy::connect::proxy::{SocksV4, SocksV5, Tunnel};
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpConnector};
+use hyper_util::client::legacy::ResponseFuture;
+use hyper_util::client::proxy::matcher::Matcher;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[cfg(feature = "rustls-tls")]
+type Connector = hyper_rustls::HttpsConnector<ProxyConnector>;
+#[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+type Connector = hyper_tls::HttpsConnector<ProxyConnector>;
+#[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+type Connector = ProxyConnector;
+
+/// An HTTP client whose connections go through a proxy.
+#[derive(Clone, Debug)]
+pub(crate) struct RawClient {
+    client: hyper_util::client::legacy::Client<Connector, BoxBody<hyper::body::Bytes, Infallible>>,
+    matcher: Arc<Matcher>,
+}
+
+impl RawClient {
+    /// Send `req`, through the proxy if its destination calls for one.
+    pub(crate) fn request(
+        &self,
+        mut req: hyper::Request<BoxBody<hyper::body::Bytes, Infallible>>,
+    ) -> ResponseFuture {
+        // plain HTTP requests go to the proxy itself rather than through a tunnel, so they must
+        // carry the proxy's credentials
+        if req.uri().scheme_str() == Some("http") {
+            let auth = self
+                .matcher
+                .intercept(req.uri())
+                .and_then(|intercept| intercept.basic_auth().cloned());
+            if let Some(auth) = auth {
+                req.headers_mut()
+                    .entry(http::header::PROXY_AUTHORIZATION)
+                    .or_insert(auth);
+            }
+        }
+        self.client.request(req)
+    }
+}
+
+/// Build a client that sends requests through `proxy`, or `None` if requests should be sent
+/// directly.
+///
+/// Proxy auto-config files and auto-detection would need a JavaScript engine to evaluate, so
+/// with those configurations requests are sent directly.
+pub(crate) fn raw_client(proxy: &ProxyConfig) -> io::Result<Option<RawClient>> {
+    let Some(matcher) = matcher(proxy) else {
+        return Ok(None);
+    };
+    let matcher = Arc::new(matcher);
+    let mut http = HttpConnector::new();
+    // the connector is also used to reach proxies, which need not be http:// URLs
+    http.enforce_http(false);
+    let connector = ProxyConnector {
+        http,
+        matcher: matcher.clone(),
+    };
+
+    #[cfg(feature = "rustls-tls")]
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()?
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(connector);
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    let connector = hyper_tls::HttpsConnector::new_with_connector(connector);
+
+    Ok(Some(RawClient {
+        client: hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build(connector),
+        matcher,
+    }))
+}
+
+/// Work out which proxy, if any, each destination should go through.
+fn matcher(proxy: &ProxyConfig) -> Option<Matcher> {
+    match proxy {
+        ProxyConfig::Direct | ProxyConfig::Autodetect | ProxyConfig::Pac(_) => None,
+        ProxyConfig::System => Some(Matcher::from_system()),
+        ProxyConfig::Manual {
+            http,
+            ssl,
+            socks,
+            socks_version,
+            no_proxy,
+        } => {
+            let socks = socks.as_ref().map(|socks| {
+                // like browsers, let SOCKS5 proxies resolve host names, but not SOCKS4 ones
+                let scheme = if *socks_version == Some(4) {
+                    "socks4"
+                } else {
+                    "socks5h"
+                };
+                format!("{}://{}", scheme, socks)
+            });
+            let http = http.as_ref().map(|http| format!("http://{}", http));
+            let https = ssl.as_ref().map(|ssl| format!("http://{}", ssl));
+            let no_proxy: Vec<_> = no_proxy
+                .iter()
+                .map(|host| host.trim_start_matches('*'))
+                .collect();
+            Some(
+                Matcher::builder()
+                    .http(http.or_else(|| socks.clone()).unwrap_or_default())
+                    .https(https.or(socks).unwrap_or_default())
+                    .no(no_proxy.join(","))
+                    .build(),
+            )
+        }
+    }
+}
+
+/// A connector that connects either directly or through a proxy.
+#[derive(Clone, Debug)]
+pub(crate) struct ProxyConnector {
+    http: HttpConnector,
+    matcher: Arc<Matcher>,
+}
+
+/// Give `dst` an explicit port, since SOCKS proxies need one.
+fn with_port(dst: &Uri) -> Result<Uri, BoxError> {
+    if dst.port().is_some() {
+        return Ok(dst.clone());
+    }
+    let port = if dst.scheme_str() == Some("https") {
+        443
+    } else {
+        80
+    };
+    let host = dst.host().ok_or("destination has no host")?;
+    Ok(Uri::builder()
+        .scheme(dst.scheme_str().unwrap_or("http"))
+        .authority(format!("{}:{}", host, port))
+        .path_and_query("/")
+        .build()?)
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = ProxyStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut http = self.http.clone();
+        let intercept = self.matcher.intercept(&dst);
+        Box::pin(async move {
+            let Some(intercept) = intercept else {
+                let io = http.call(dst).await?;
+                return Ok(ProxyStream { io, proxied: false });
+            };
+            let proxy = intercept.uri().clone();
+            let io = match proxy.scheme_str() {
+                Some(scheme @ ("socks5" | "socks5h")) => {
+                    let mut socks = SocksV5::new(proxy.clone(), http).local_dns(scheme == "socks5");
+                    if let Some((user, pass)) = intercept.raw_auth() {
+                        socks = socks.with_auth(user.to_string(), pass.to_string());
+                    }
+                    socks.call(with_port(&dst)?).await?
+                }
+                Some(scheme @ ("socks4" | "socks4a")) => {
+                    SocksV4::new(proxy.clone(), http)
+                        .local_dns(scheme == "socks4")
+                        .call(with_port(&dst)?)
+                        .await?
+                }
+                _ if dst.scheme_str() == Some("https") => {
+                    // tunnel through the proxy, so that TLS is end-to-end
+                    let mut tunnel = Tunnel::new(proxy.clone(), http);
+                    if let Some(auth) = intercept.basic_auth() {
+                        tunnel = tunnel.with_auth(auth.clone());
+                    }
+                    tunnel.call(dst).await?
+                }
+                _ => {
+                    // plain HTTP is sent to the proxy as is, with the full URL in the request
+                    let io = http.call(proxy).await?;
+                    return Ok(ProxyStream { io, proxied: true });
+                }
+            };
+            Ok(ProxyStream { io, proxied: false })
+        })
+    }
+}
+
+/// A connection made by [`ProxyConnector`].
+#[derive(Debug)]
+pub(crate) struct ProxyStream {
+    io: TokioIo<TcpStream>,
+    /// Whether requests on this connection go to a proxy rather than to their destination.
+    proxied: bool,
+}
+
+impl Connection for ProxyStream {
+    fn connected(&self) -> Connected {
+        self.io.connected().proxy(self.proxied)
+    }
+}
+
+impl Read for ProxyStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl Write for ProxyStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
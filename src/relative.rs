@@ -0,0 +1,256 @@
+//! Finding elements by where they are on the page relative to other elements.
+//!
+//! A [`RelativeLocator`] starts from the elements that match a [`Locator`], and keeps those that
+//! are above, below, to the left of, to the right of, or near one or more anchor [`Element`]s.
+//! Like Selenium's relative locators, this is handy when the element you want has nothing to
+//! identify it but the label next to it:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), fantoccini::error::CmdError> {
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let c = fantoccini::ClientBuilder::native().connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = fantoccini::ClientBuilder::rustls().expect("rustls initialization").connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! use fantoccini::relative::RelativeLocator;
+//! use fantoccini::Locator;
+//!
+//! let label = c
+//!     .find(Locator::Text { text: "Email", exact: true })
+//!     .await?;
+//! let email = RelativeLocator::new(Locator::TagName("input"))
+//!     .right_of(&label)
+//!     .resolve(&c)
+//!     .await?;
+//! email.send_keys("user@example.com").await?;
+//!
+//! let heading = c.find(Locator::TagName("h1")).await?;
+//! let buttons = RelativeLocator::new(Locator::TagName("button"))
+//!     .near(&heading, 50.0)
+//!     .resolve_all(&c)
+//!     .await?;
+//! # c.close().await
+//! # }
+//! ```
+//!
+//! The positions of all candidates and anchors are measured with a single script, using the
+//! bounding boxes of the elements in CSS pixels. Elements that are not rendered have empty
+//! bounding boxes at the origin, and so may match unexpectedly.
+use crate::elements::Element;
+use crate::error;
+use crate::wd::{Locator, LocatorBuf};
+use crate::Client;
+use serde_json::Value as Json;
+
+/// Get the bounding box of each argument, relative to the document.
+const MEASURE: &str = "return Array.from(arguments).map((el) => {
+    const r = el.getBoundingClientRect();
+    return [r.left + window.scrollX, r.top + window.scrollY, r.width, r.height];
+});";
+
+/// Where a candidate must be relative to an anchor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Position {
+    Above,
+    Below,
+    LeftOf,
+    RightOf,
+    /// Within the given number of pixels.
+    Near(f64),
+}
+
+/// The bounding box of an element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    fn right(&self) -> f64 {
+        self.left + self.width
+    }
+
+    fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (self.left + self.width / 2.0, self.top + self.height / 2.0)
+    }
+
+    /// Whether this rectangle is at `position` relative to `anchor`.
+    fn is(&self, position: Position, anchor: &Rect) -> bool {
+        match position {
+            Position::Above => self.bottom() <= anchor.top,
+            Position::Below => self.top >= anchor.bottom(),
+            Position::LeftOf => self.right() <= anchor.left,
+            Position::RightOf => self.left >= anchor.right(),
+            Position::Near(distance) => self.gap(anchor) <= distance,
+        }
+    }
+
+    /// The shortest distance between the edges of this rectangle and `other`, or zero if they
+    /// overlap.
+    fn gap(&self, other: &Rect) -> f64 {
+        let dx = (other.left - self.right())
+            .max(self.left - other.right())
+            .max(0.0);
+        let dy = (other.top - self.bottom())
+            .max(self.top - other.bottom())
+            .max(0.0);
+        dx.hypot(dy)
+    }
+
+    /// The distance between the centers of this rectangle and `other`.
+    fn distance(&self, other: &Rect) -> f64 {
+        let (x1, y1) = self.center();
+        let (x2, y2) = other.center();
+        (x1 - x2).hypot(y1 - y2)
+    }
+}
+
+/// A locator that filters the elements matched by another locator by their position relative to
+/// anchor elements.
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Clone, Debug)]
+pub struct RelativeLocator {
+    base: LocatorBuf,
+    anchors: Vec<(Position, Element)>,
+}
+
+impl RelativeLocator {
+    /// Start from the elements that match `base`.
+    pub fn new(base: impl Into<LocatorBuf>) -> Self {
+        RelativeLocator {
+            base: base.into(),
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Keep only elements whose bottom edge is at or above the top edge of `anchor`.
+    #[must_use]
+    pub fn above(self, anchor: &Element) -> Self {
+        self.anchored(Position::Above, anchor)
+    }
+
+    /// Keep only elements whose top edge is at or below the bottom edge of `anchor`.
+    #[must_use]
+    pub fn below(self, anchor: &Element) -> Self {
+        self.anchored(Position::Below, anchor)
+    }
+
+    /// Keep only elements whose right edge is at or to the left of the left edge of `anchor`.
+    #[must_use]
+    pub fn left_of(self, anchor: &Element) -> Self {
+        self.anchored(Position::LeftOf, anchor)
+    }
+
+    /// Keep only elements whose left edge is at or to the right of the right edge of `anchor`.
+    #[must_use]
+    pub fn right_of(self, anchor: &Element) -> Self {
+        self.anchored(Position::RightOf, anchor)
+    }
+
+    /// Keep only elements that are at most `distance` CSS pixels away from `anchor`, measured
+    /// between their closest edges.
+    #[must_use]
+    pub fn near(self, anchor: &Element, distance: f64) -> Self {
+        self.anchored(Position::Near(distance), anchor)
+    }
+
+    fn anchored(mut self, position: Position, anchor: &Element) -> Self {
+        self.anchors.push((position, anchor.clone()));
+        self
+    }
+
+    /// The locator that candidates are found with.
+    pub fn base(&self) -> Locator<'_> {
+        self.base.as_locator()
+    }
+
+    /// Find the element that matches the base locator and all conditions, and is closest to the
+    /// first anchor.
+    pub async fn resolve(&self, client: &Client) -> Result<Element, error::CmdError> {
+        self.resolve_all(client)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                error::CmdError::Standard(error::WebDriver::new(
+                    error::ErrorStatus::NoSuchElement,
+                    format!(
+                        "no element matching {:?} is in the given position",
+                        self.base
+                    ),
+                ))
+            })
+    }
+
+    /// Find all elements that match the base locator and all conditions.
+    ///
+    /// The elements are sorted by the distance between their center and the center of the first
+    /// anchor, closest first, and otherwise in document order. Anchors are never included.
+    pub async fn resolve_all(&self, client: &Client) -> Result<Vec<Element>, error::CmdError> {
+        let anchor_ids: Vec<_> = self.anchors.iter().map(|(_, a)| a.element_id()).collect();
+        let candidates: Vec<_> = client
+            .find_all(self.base.as_locator())
+            .await?
+            .into_iter()
+            .filter(|e| !anchor_ids.contains(&e.element_id()))
+            .collect();
+        if candidates.is_empty() || self.anchors.is_empty() {
+            return Ok(candidates);
+        }
+
+        let args = self
+            .anchors
+            .iter()
+            .map(|(_, anchor)| anchor)
+            .chain(&candidates)
+            .map(|e| via_json!(e))
+            .collect();
+        let res = client.execute(MEASURE, args).await?;
+        let rects = parse_rects(&res).ok_or(error::CmdError::NotW3C(res.clone()))?;
+        if rects.len() != self.anchors.len() + candidates.len() {
+            return Err(error::CmdError::NotW3C(res));
+        }
+        let (anchors, rects) = rects.split_at(self.anchors.len());
+
+        let mut found: Vec<_> = candidates
+            .into_iter()
+            .zip(rects)
+            .filter(|(_, rect)| {
+                self.anchors
+                    .iter()
+                    .zip(anchors)
+                    .all(|((position, _), anchor)| rect.is(*position, anchor))
+            })
+            .map(|(e, rect)| (rect.distance(&anchors[0]), e))
+            .collect();
+        found.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Ok(found.into_iter().map(|(_, e)| e).collect())
+    }
+}
+
+/// Parse the result of [`MEASURE`].
+fn parse_rects(res: &Json) -> Option<Vec<Rect>> {
+    res.as_array()?
+        .iter()
+        .map(|rect| {
+            let rect = rect.as_array()?;
+            let value = |i: usize| rect.get(i).and_then(Json::as_f64);
+            Some(Rect {
+                left: value(0)?,
+                top: value(1)?,
+                width: value(2)?,
+                height: value(3)?,
+            })
+        })
+        .collect()
+}
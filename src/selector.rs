@@ -0,0 +1,250 @@
+//! Selectors that chain locators across elements, shadow roots and frames.
+//!
+//! A [`Locator`] finds elements in a single document or subtree. Elements in web components and
+//! `<iframe>`s often take several lookups to reach: find the host element, then look in its
+//! shadow root, and so on. A [`Selector`] describes such a path as a list of [`Step`]s, each of
+//! which is resolved relative to the element found by the step before it:
+//!
+//! ```no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), fantoccini::error::CmdError> {
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! # let c = fantoccini::ClientBuilder::native().connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = fantoccini::ClientBuilder::rustls().expect("rustls initialization").connect("http://localhost:4444").await.expect("failed to connect to WebDriver");
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! use fantoccini::selector::Selector;
+//! use fantoccini::Locator;
+//!
+//! // <checkout-page> >>> <iframe id="payment"> | <input name="card">
+//! let card = Selector::new(Locator::Css("checkout-page"))
+//!     .pierce(Locator::Id("payment"))
+//!     .frame(Locator::Name("card"));
+//! card.resolve(&c).await?.send_keys("4242 4242 4242 4242").await?;
+//! # c.close().await
+//! # }
+//! ```
+//!
+//! Since a selector owns its locators, it can be stored in page objects and built at runtime. If
+//! a step matches nothing, the error says which step that was.
+use crate::elements::Element;
+use crate::error;
+use crate::wd::{Locator, LocatorBuf};
+use crate::Client;
+use std::fmt;
+
+/// One step of a [`Selector`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Step {
+    /// Find an element below the element found by the previous step, or in the current browsing
+    /// context if this is the first step.
+    Descend(LocatorBuf),
+    /// Find an element in the shadow root of the element found by the previous step.
+    ///
+    /// This is the `>>>` combinator of some selector engines.
+    Pierce(LocatorBuf),
+    /// Switch to the frame of the `<iframe>` or `<frame>` found by the previous step, and find an
+    /// element in its document.
+    Frame(LocatorBuf),
+}
+
+impl Step {
+    /// The locator this step searches with.
+    pub fn locator(&self) -> Locator<'_> {
+        match self {
+            Step::Descend(locator) | Step::Pierce(locator) | Step::Frame(locator) => {
+                locator.as_locator()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Descend(locator) => write!(f, "{:?}", locator),
+            Step::Pierce(locator) => write!(f, ">>> {:?}", locator),
+            Step::Frame(locator) => write!(f, "| {:?}", locator),
+        }
+    }
+}
+
+/// A chain of locators, resolved one [`Step`] at a time.
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+impl Selector {
+    /// Start a selector that finds an element in the current browsing context.
+    pub fn new(locator: impl Into<LocatorBuf>) -> Self {
+        Selector {
+            steps: vec![Step::Descend(locator.into())],
+        }
+    }
+
+    /// Continue with an element below the one found so far.
+    #[must_use]
+    pub fn descend(mut self, locator: impl Into<LocatorBuf>) -> Self {
+        self.steps.push(Step::Descend(locator.into()));
+        self
+    }
+
+    /// Continue with an element in the shadow root of the one found so far.
+    #[must_use]
+    pub fn pierce(mut self, locator: impl Into<LocatorBuf>) -> Self {
+        self.steps.push(Step::Pierce(locator.into()));
+        self
+    }
+
+    /// Continue with an element in the frame of the one found so far.
+    ///
+    /// Resolving the selector switches the session to that frame, and it stays there afterwards.
+    /// Use [`Client::enter_parent_frame`] or [`Client::enter_frame`] to get back out.
+    #[must_use]
+    pub fn frame(mut self, locator: impl Into<LocatorBuf>) -> Self {
+        self.steps.push(Step::Frame(locator.into()));
+        self
+    }
+
+    /// The steps of this selector, in the order they are resolved.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Find the element this selector describes.
+    ///
+    /// Each step uses the first element that matches the step before it. If a step fails, the
+    /// returned error says which one it was, and keeps the [`ErrorStatus`](error::ErrorStatus)
+    /// the step failed with, so that, for example,
+    /// [`is_no_such_element`](error::CmdError::is_no_such_element) still works.
+    pub async fn resolve(&self, client: &Client) -> Result<Element, error::CmdError> {
+        let (last, path) = self
+            .steps
+            .split_last()
+            .expect("selectors have at least one step");
+        let parent = self.resolve_path(client, path).await?;
+        self.resolve_step(client, parent.as_ref(), last, path.len())
+            .await
+            .map_err(|e| self.failed_at(path.len(), e))
+    }
+
+    /// Find all elements that match the last step of this selector.
+    ///
+    /// All steps but the last use the first element that matches them, as in
+    /// [`resolve`](Self::resolve).
+    pub async fn resolve_all(&self, client: &Client) -> Result<Vec<Element>, error::CmdError> {
+        let (last, path) = self
+            .steps
+            .split_last()
+            .expect("selectors have at least one step");
+        let parent = self.resolve_path(client, path).await?;
+        let res = async {
+            match (last, parent.as_ref()) {
+                (Step::Descend(locator), None) => client.find_all(locator.as_locator()).await,
+                (Step::Descend(locator), Some(parent)) => {
+                    parent.find_all(locator.as_locator()).await
+                }
+                (Step::Pierce(locator), Some(parent)) => {
+                    parent
+                        .shadow_root()
+                        .await?
+                        .find_all(locator.as_locator())
+                        .await
+                }
+                (Step::Frame(locator), Some(parent)) => {
+                    parent.enter_frame().await?;
+                    client.find_all(locator.as_locator()).await
+                }
+                (_, None) => unreachable!("selectors start with a descend step"),
+            }
+        }
+        .await;
+        res.map_err(|e| self.failed_at(path.len(), e))
+    }
+
+    /// Resolve `path`, the steps before the last one, to the element the last step starts from.
+    async fn resolve_path(
+        &self,
+        client: &Client,
+        path: &[Step],
+    ) -> Result<Option<Element>, error::CmdError> {
+        let mut current = None;
+        for (i, step) in path.iter().enumerate() {
+            let found = self
+                .resolve_step(client, current.as_ref(), step, i)
+                .await
+                .map_err(|e| self.failed_at(i, e))?;
+            current = Some(found);
+        }
+        Ok(current)
+    }
+
+    /// Resolve the step at `index` from the element found by the step before it, if any.
+    async fn resolve_step(
+        &self,
+        client: &Client,
+        parent: Option<&Element>,
+        step: &Step,
+        index: usize,
+    ) -> Result<Element, error::CmdError> {
+        match (step, parent) {
+            (Step::Descend(locator), None) => client.find(locator.as_locator()).await,
+            (Step::Descend(locator), Some(parent)) => parent.find(locator.as_locator()).await,
+            (Step::Pierce(locator), Some(parent)) => {
+                parent.shadow_root().await?.find(locator.as_locator()).await
+            }
+            (Step::Frame(locator), Some(parent)) => {
+                parent.enter_frame().await?;
+                client.find(locator.as_locator()).await
+            }
+            (_, None) => unreachable!("step {} of a selector has no element to start from", index),
+        }
+    }
+
+    /// Say in `e` that it happened at the step at `index`.
+    fn failed_at(&self, index: usize, e: error::CmdError) -> error::CmdError {
+        match e {
+            error::CmdError::Standard(mut w) => {
+                w.message = format!(
+                    "step {} of {} (`{}` in `{}`) failed: {}",
+                    index + 1,
+                    self.steps.len(),
+                    self.steps[index],
+                    self,
+                    w.message
+                )
+                .into();
+                error::CmdError::Standard(w)
+            }
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LocatorBuf> for Selector {
+    fn from(locator: LocatorBuf) -> Self {
+        Selector::new(locator)
+    }
+}
+
+impl From<Locator<'_>> for Selector {
+    fn from(locator: Locator<'_>) -> Self {
+        Selector::new(locator)
+    }
+}
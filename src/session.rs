@@ -9,12 +9,15 @@ use hyper_util::client::legacy::connect;
 use hyper_util::rt::TokioExecutor;
 use serde_json::Value as Json;
 use std::convert::Infallible;
+use std::fmt;
 use std::future::Future;
 use std::io;
 use std::mem;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
 use std::task::{ready, Poll};
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
 use webdriver::command::WebDriverCommand;
 use webdriver::response::NewSessionResponse;
@@ -31,6 +34,7 @@ pub(crate) enum Cmd {
     Shutdown,
     Persist,
     GetUa,
+    GetWebDriverUrl,
     Raw {
         req: hyper::Request<http_body_util::combinators::BoxBody<hyper::body::Bytes, Infallible>>,
         rsp: oneshot::Sender<
@@ -149,6 +153,12 @@ impl WebDriverCompatibleCommand for Wcmd {
             WebDriverCommand::GetShadowRoot(ref we) => {
                 base.join(&format!("element/{}/shadow", we.0))
             }
+            WebDriverCommand::GetComputedRole(ref we) => {
+                base.join(&format!("element/{}/computedrole", we.0))
+            }
+            WebDriverCommand::GetComputedLabel(ref we) => {
+                base.join(&format!("element/{}/computedlabel", we.0))
+            }
             WebDriverCommand::Print(..) => base.join("print"),
             WebDriverCommand::Status => unreachable!(),
             _ => unimplemented!("{:?}", self),
@@ -291,6 +301,270 @@ impl WebDriverCompatibleCommand for Wcmd {
     fn is_new_session(&self) -> bool {
         matches!(self, WebDriverCommand::NewSession(..))
     }
+
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            WebDriverCommand::Status
+                | WebDriverCommand::GetCurrentUrl
+                | WebDriverCommand::GetTitle
+                | WebDriverCommand::GetPageSource
+                | WebDriverCommand::GetWindowHandle
+                | WebDriverCommand::GetWindowHandles
+                | WebDriverCommand::GetWindowRect
+                | WebDriverCommand::FindElement(..)
+                | WebDriverCommand::FindElements(..)
+                | WebDriverCommand::FindElementElement(..)
+                | WebDriverCommand::FindElementElements(..)
+                | WebDriverCommand::FindShadowRootElement(..)
+                | WebDriverCommand::FindShadowRootElements(..)
+                | WebDriverCommand::GetActiveElement
+                | WebDriverCommand::IsDisplayed(..)
+                | WebDriverCommand::IsSelected(..)
+                | WebDriverCommand::IsEnabled(..)
+                | WebDriverCommand::GetElementAttribute(..)
+                | WebDriverCommand::GetElementProperty(..)
+                | WebDriverCommand::GetCSSValue(..)
+                | WebDriverCommand::GetElementText(..)
+                | WebDriverCommand::GetElementTagName(..)
+                | WebDriverCommand::GetElementRect(..)
+                | WebDriverCommand::GetShadowRoot(..)
+                | WebDriverCommand::GetComputedRole(..)
+                | WebDriverCommand::GetComputedLabel(..)
+                | WebDriverCommand::GetCookies
+                | WebDriverCommand::GetNamedCookie(..)
+                | WebDriverCommand::GetTimeouts
+                | WebDriverCommand::GetAlertText
+                | WebDriverCommand::TakeScreenshot
+                | WebDriverCommand::TakeElementScreenshot(..)
+                | WebDriverCommand::Print(..)
+        )
+    }
+
+    fn is_idempotent(&self) -> bool {
+        self.is_read_only()
+            || matches!(
+                self,
+                WebDriverCommand::SetTimeouts(..)
+                    | WebDriverCommand::SetWindowRect(..)
+                    | WebDriverCommand::SwitchToWindow(..)
+                    | WebDriverCommand::AddCookie(..)
+                    | WebDriverCommand::DeleteCookie(..)
+                    | WebDriverCommand::DeleteCookies
+                    | WebDriverCommand::ReleaseActions
+            )
+    }
+}
+
+impl Cmd {
+    /// Whether this command may run concurrently with other such commands.
+    fn is_concurrent(&self) -> bool {
+        match self {
+            // raw requests do not go through the browser at all
+            Cmd::Raw { .. } => true,
+            Cmd::WebDriver(cmd) => cmd.is_read_only(),
+            // these are answered locally, and never wait for anything
+            Cmd::SetUa(..)
+            | Cmd::GetSessionId
+            | Cmd::GetUa
+            | Cmd::GetWebDriverUrl
+            | Cmd::Persist => true,
+            Cmd::Shutdown => false,
+        }
+    }
+}
+
+/// An HTTP request on its way to the WebDriver server.
+///
+/// See [`Transport`].
+#[derive(Debug, Clone)]
+pub struct WireRequest {
+    /// The HTTP request method.
+    pub method: http::Method,
+    /// The full URL of the endpoint, including the WebDriver server's base URL.
+    pub url: url::Url,
+    /// The request headers.
+    pub headers: http::HeaderMap,
+    /// The (JSON) request body, if any.
+    pub body: Option<String>,
+}
+
+/// An HTTP response from the WebDriver server.
+///
+/// See [`Transport`].
+#[derive(Debug, Clone)]
+pub struct WireResponse {
+    /// The HTTP status code.
+    pub status: http::StatusCode,
+    /// The response headers.
+    pub headers: http::HeaderMap,
+    /// The response body.
+    pub body: String,
+}
+
+/// The future returned by a [`Transport`].
+pub type TransportFuture =
+    Pin<Box<dyn Future<Output = Result<WireResponse, error::CmdError>> + Send + 'static>>;
+
+/// The rest of the transport chain, which sends a [`WireRequest`] to the WebDriver server.
+///
+/// See [`Transport`].
+pub struct Next {
+    send: Box<dyn FnOnce(WireRequest) -> TransportFuture + Send>,
+}
+
+impl Next {
+    fn new(send: impl FnOnce(WireRequest) -> TransportFuture + Send + 'static) -> Self {
+        Next {
+            send: Box::new(send),
+        }
+    }
+
+    /// Send `request` to the WebDriver server.
+    pub fn run(self, request: WireRequest) -> TransportFuture {
+        (self.send)(request)
+    }
+}
+
+impl fmt::Debug for Next {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next").finish_non_exhaustive()
+    }
+}
+
+/// A hook that sees every HTTP exchange between a [`Client`] and the WebDriver server.
+///
+/// A transport is handed each request a session makes, including the one that creates the
+/// session and the one that deletes it. It can inspect or modify the request, pass it on to the
+/// server using [`Next::run`], and inspect or modify the response. It can also answer the
+/// request itself without involving the server at all.
+///
+/// Requests made with [`Client::raw_client_for`] do not go through the transport.
+///
+/// See [`ClientBuilder::transport`](crate::ClientBuilder::transport), and the
+/// [`cassette`](crate::cassette) module for an example.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Process a single request.
+    fn send(&self, request: WireRequest, next: Next) -> TransportFuture;
+}
+
+/// A WebDriver command on its way to the WebDriver server.
+///
+/// See [`Middleware`].
+#[derive(Debug)]
+pub struct CommandRequest {
+    command: Box<dyn WebDriverCompatibleCommand + Send>,
+    /// The HTTP request that will be sent for the command.
+    ///
+    /// Changes made to the request by a middleware are seen by the middleware after it, and are
+    /// then sent as-is.
+    pub request: WireRequest,
+}
+
+impl CommandRequest {
+    /// The command being issued.
+    pub fn command(&self) -> &(dyn WebDriverCompatibleCommand + Send) {
+        &*self.command
+    }
+}
+
+/// The future returned by a [`Middleware`].
+pub type CommandFuture =
+    Pin<Box<dyn Future<Output = Result<Json, error::CmdError>> + Send + 'static>>;
+
+/// The rest of the middleware stack, which eventually sends a [`CommandRequest`] to the
+/// WebDriver server.
+///
+/// See [`Middleware`].
+pub struct NextCommand {
+    send: Box<dyn FnOnce(CommandRequest) -> CommandFuture + Send>,
+}
+
+impl NextCommand {
+    /// Build the stack of middleware from `stack[i]` inwards, ending with `send`.
+    fn chain(
+        stack: Arc<[Arc<dyn Middleware>]>,
+        i: usize,
+        send: Arc<dyn Fn(CommandRequest) -> CommandFuture + Send + Sync>,
+    ) -> Self {
+        NextCommand {
+            send: Box::new(move |request| match stack.get(i).cloned() {
+                Some(middleware) => middleware.call(request, Self::chain(stack, i + 1, send)),
+                None => send(request),
+            }),
+        }
+    }
+
+    /// Pass `request` on to the rest of the stack, and eventually to the WebDriver server.
+    pub fn run(self, request: CommandRequest) -> CommandFuture {
+        (self.send)(request)
+    }
+}
+
+impl fmt::Debug for NextCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NextCommand").finish_non_exhaustive()
+    }
+}
+
+/// A layer that sees every WebDriver command a [`Client`] issues, and its result.
+///
+/// A middleware is given each command along with the HTTP request that was computed for it,
+/// before the request is sent. It can change the request (for example to add headers, or to
+/// rewrite the endpoint), pass it on using [`NextCommand::run`], and then inspect or change the
+/// JSON result. It can also produce a result itself without passing the command on at all.
+///
+/// Middleware is added with [`ClientBuilder::middleware`](crate::ClientBuilder::middleware).
+/// The middleware added first is the outermost one, and so sees each command first and each
+/// result last. Unlike a [`Transport`], middleware does not see the HTTP response, but rather the
+/// parsed result of the command. Middleware also does not see the request that deletes the
+/// session when the client is closed.
+///
+/// ```
+/// use fantoccini::wd::{CommandFuture, CommandRequest, Middleware, NextCommand};
+///
+/// /// Authenticate with a cloud grid.
+/// #[derive(Debug)]
+/// struct GridToken(http::HeaderValue);
+///
+/// impl Middleware for GridToken {
+///     fn call(&self, mut request: CommandRequest, next: NextCommand) -> CommandFuture {
+///         request
+///             .request
+///             .headers
+///             .insert("x-grid-token", self.0.clone());
+///         next.run(request)
+///     }
+/// }
+/// ```
+pub trait Middleware: fmt::Debug + Send + Sync {
+    /// Process a single command.
+    fn call(&self, request: CommandRequest, next: NextCommand) -> CommandFuture;
+}
+
+/// Configuration for a [`Session`] that is set through [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Config {
+    pub(crate) transport: Option<Arc<dyn Transport>>,
+    pub(crate) concurrent: bool,
+    pub(crate) retry: Option<wd::RetryPolicy>,
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) auth: Option<http::HeaderValue>,
+    pub(crate) headers: http::HeaderMap,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) proxy: Option<crate::options::ProxyConfig>,
+    #[cfg(feature = "driver")]
+    pub(crate) driver: Option<Arc<crate::driver::Process>>,
+}
+
+/// The value of an `Authorization` header for HTTP basic authentication.
+pub(crate) fn basic_auth(username: &str, password: &str) -> http::HeaderValue {
+    let credentials =
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    let mut auth = http::HeaderValue::from_str(&format!("Basic {}", credentials))
+        .expect("base64 is always a valid header value");
+    auth.set_sensitive(true);
+    auth
 }
 
 impl From<Wcmd> for Cmd {
@@ -303,6 +577,9 @@ impl From<Wcmd> for Cmd {
 pub(crate) struct Task {
     request: Cmd,
     ack: Ack,
+    timeout: Option<Duration>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl Client {
@@ -312,9 +589,32 @@ impl Client {
     {
         let (tx, rx) = oneshot::channel();
         let cmd = cmd.into();
+
+        // the span is entered by the session, but is created here so that it is a child of the
+        // caller's span
+        #[cfg(feature = "tracing")]
+        let span = if let Cmd::WebDriver(..) = cmd {
+            use tracing::field::Empty;
+            tracing::debug_span!(
+                "webdriver_command",
+                method = Empty,
+                path = Empty,
+                session_id = Empty,
+                status = Empty,
+                duration_ms = Empty,
+                error = Empty,
+                error_status = Empty,
+            )
+        } else {
+            tracing::Span::none()
+        };
+
         let r = self.tx.send(Task {
             request: cmd,
             ack: tx,
+            timeout: self.timeout,
+            #[cfg(feature = "tracing")]
+            span,
         });
 
         if r.is_err() {
@@ -347,7 +647,7 @@ enum Ongoing {
     Break,
     Shutdown {
         ack: Option<Ack>,
-        fut: hyper_util::client::legacy::ResponseFuture,
+        fut: TransportFuture,
     },
     WebDriver {
         ack: Ack,
@@ -379,7 +679,7 @@ impl Ongoing {
             Ongoing::None => OngoingResult::Continue,
             Ongoing::Break => OngoingResult::Break,
             Ongoing::Shutdown { mut fut, ack } => {
-                if Pin::new(&mut fut).poll(cx).is_pending() {
+                if fut.as_mut().poll(cx).is_pending() {
                     *self = Ongoing::Shutdown { fut, ack };
                     return Poll::Pending;
                 }
@@ -389,7 +689,11 @@ impl Ongoing {
                 }
                 OngoingResult::Break
             }
-            Ongoing::WebDriver { mut fut, ack } => {
+            Ongoing::WebDriver { mut fut, mut ack } => {
+                if ack.poll_closed(cx).is_ready() {
+                    // the caller gave up on the command, so abort the request
+                    return Poll::Ready(OngoingResult::Continue);
+                }
                 let rsp = if let Poll::Ready(v) = fut.as_mut().poll(cx) {
                     v
                 } else {
@@ -436,15 +740,22 @@ where
     C: connect::Connect,
 {
     ongoing: Ongoing,
+    /// Read-only commands that are in flight concurrently, if enabled.
+    concurrent: Vec<Ongoing>,
+    /// A command that is waiting for all concurrent commands to complete.
+    blocked: Option<Task>,
     rx: mpsc::UnboundedReceiver<Task>,
     client: hyper_util::client::legacy::Client<
         C,
         http_body_util::combinators::BoxBody<hyper::body::Bytes, Infallible>,
     >,
+    /// The client for raw requests, if they go through a proxy.
+    raw: Option<crate::proxy::RawClient>,
     wdb: url::Url,
     session: Option<String>,
     ua: Option<String>,
     persist: bool,
+    config: Config,
 }
 
 impl<C> Future for Session<C>
@@ -455,6 +766,10 @@ where
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
+            // concurrent commands are never the NewSession command, and never end the session
+            self.concurrent
+                .retain_mut(|ongoing| ongoing.poll(false, cx).is_pending());
+
             if self.ongoing.is_some() {
                 let has_session = self.session.is_none();
                 match ready!(self.ongoing.poll(has_session, cx)) {
@@ -468,53 +783,37 @@ where
 
             // if we get here, there can be no ongoing request.
             // queue a new one.
-            if let Some(Task { request, ack }) = ready!(Pin::new(&mut self.rx).poll_recv(cx)) {
-                // some calls are just local housekeeping calls
-                match request {
-                    Cmd::GetSessionId => {
-                        let _ = ack.send(Ok(self
-                            .session
-                            .clone()
-                            .map(Json::String)
-                            .unwrap_or(Json::Null)));
-                    }
-                    Cmd::SetUa(ua) => {
-                        self.ua = Some(ua);
-                        let _ = ack.send(Ok(Json::Null));
-                    }
-                    Cmd::GetUa => {
-                        let _ =
-                            ack.send(Ok(self.ua.clone().map(Json::String).unwrap_or(Json::Null)));
-                    }
-                    Cmd::Raw { req, rsp } => {
-                        self.ongoing = Ongoing::Raw {
-                            ack,
-                            ret: rsp,
-                            fut: self.client.request(req),
-                        };
-                    }
-                    Cmd::Persist => {
-                        self.persist = true;
-                        let _ = ack.send(Ok(Json::Null));
-                    }
-                    Cmd::Shutdown => {
-                        // explicit client shutdown
-                        self.shutdown(Some(ack));
-                    }
-                    Cmd::WebDriver(request) => {
-                        self.ongoing = Ongoing::WebDriver {
-                            ack,
-                            fut: Box::pin(self.issue_wd_cmd(request)),
-                        };
-                    }
-                };
+            let task = if let Some(task) = self.blocked.take() {
+                task
+            } else if let Some(task) = ready!(Pin::new(&mut self.rx).poll_recv(cx)) {
+                task
+            } else if !self.concurrent.is_empty() {
+                // let the concurrent commands finish before we shut down
+                return Poll::Pending;
             } else {
                 // we're shutting down!
                 if self.persist {
                     self.ongoing = Ongoing::Break;
                 } else {
-                    self.shutdown(None);
+                    let timeout = self.config.timeout;
+                    self.shutdown(None, timeout);
+                }
+                continue;
+            };
+
+            if self.config.concurrent && self.session.is_some() && task.request.is_concurrent() {
+                self.dispatch(task);
+                let ongoing = mem::replace(&mut self.ongoing, Ongoing::None);
+                if ongoing.is_some() {
+                    // it'll get polled at the top of the loop
+                    self.concurrent.push(ongoing);
                 }
+            } else if !self.concurrent.is_empty() {
+                // this command must be ordered after all the ones in flight
+                self.blocked = Some(task);
+                return Poll::Pending;
+            } else {
+                self.dispatch(task);
             }
         }
 
@@ -529,40 +828,172 @@ where
     fn new(
         rx: mpsc::UnboundedReceiver<Task>,
         client: hyper_util::client::legacy::Client<C, BoxBody<hyper::body::Bytes, Infallible>>,
+        raw: Option<crate::proxy::RawClient>,
         wdb_url: url::Url,
         session_id: Option<impl Into<String>>,
+        config: Config,
     ) -> Self {
         Session {
             ongoing: Ongoing::None,
+            concurrent: Vec::new(),
+            blocked: None,
             rx,
             client,
+            raw,
             wdb: wdb_url,
             session: session_id.map(Into::into),
             ua: None,
             persist: false,
+            config,
+        }
+    }
+
+    fn dispatch(&mut self, task: Task) {
+        #[cfg(feature = "tracing")]
+        let span = task.span;
+        let Task {
+            request,
+            ack,
+            timeout,
+            ..
+        } = task;
+        let timeout = timeout.or(self.config.timeout);
+
+        // some calls are just local housekeeping calls
+        match request {
+            Cmd::GetSessionId => {
+                let _ = ack.send(Ok(self
+                    .session
+                    .clone()
+                    .map(Json::String)
+                    .unwrap_or(Json::Null)));
+            }
+            Cmd::SetUa(ua) => {
+                self.ua = Some(ua);
+                let _ = ack.send(Ok(Json::Null));
+            }
+            Cmd::GetUa => {
+                let _ = ack.send(Ok(self.ua.clone().map(Json::String).unwrap_or(Json::Null)));
+            }
+            Cmd::GetWebDriverUrl => {
+                let _ = ack.send(Ok(Json::String(self.wdb.to_string())));
+            }
+            Cmd::Raw { req, rsp } => {
+                self.ongoing = Ongoing::Raw {
+                    ack,
+                    ret: rsp,
+                    fut: match self.raw {
+                        Some(ref raw) => raw.request(req),
+                        None => self.client.request(req),
+                    },
+                };
+            }
+            Cmd::Persist => {
+                self.persist = true;
+                let _ = ack.send(Ok(Json::Null));
+            }
+            Cmd::Shutdown => {
+                // explicit client shutdown
+                self.shutdown(Some(ack), timeout);
+            }
+            Cmd::WebDriver(_) if ack.is_closed() => {
+                // the caller is no longer waiting for the result
+            }
+            Cmd::WebDriver(request) => {
+                let fut = self.issue_wd_cmd(request, timeout);
+                #[cfg(feature = "tracing")]
+                let fut = tracing::Instrument::instrument(fut, span);
+                self.ongoing = Ongoing::WebDriver {
+                    ack,
+                    fut: Box::pin(fut),
+                };
+            }
+        };
+    }
+
+    /// Send a request to the WebDriver server through the configured [`Transport`], if any.
+    fn exchange(&self, request: WireRequest) -> TransportFuture {
+        self.exchanger()(request)
+    }
+
+    /// Like [`Session::exchange`], but usable after the session has been released.
+    fn exchanger(&self) -> impl Fn(WireRequest) -> TransportFuture + Clone + Send + Sync + 'static {
+        let client = self.client.clone();
+        let transport = self.config.transport.clone();
+        move |request| {
+            let client = client.clone();
+            let next = Next::new(move |request| Box::pin(Self::send(client, request)));
+            match transport {
+                Some(ref transport) => transport.send(request, next),
+                None => next.run(request),
+            }
         }
     }
 
-    fn shutdown(&mut self, ack: Option<Ack>) {
+    /// Send a request to the WebDriver server over HTTP.
+    async fn send(
+        client: hyper_util::client::legacy::Client<C, BoxBody<hyper::body::Bytes, Infallible>>,
+        request: WireRequest,
+    ) -> Result<WireResponse, error::CmdError> {
+        let mut req = hyper::Request::builder()
+            .method(request.method)
+            .uri(request.url.as_str());
+        if let Some(headers) = req.headers_mut() {
+            *headers = request.headers;
+        }
+        let req = if let Some(body) = request.body {
+            req = req.header(hyper::header::CONTENT_LENGTH, body.len());
+            req.body(BoxBody::new(body)).unwrap()
+        } else {
+            req.body(BoxBody::new(http_body_util::Empty::new()))
+                .unwrap()
+        };
+
+        let res = client.request(req).await?;
+        let status = res.status();
+        let headers = res.headers().clone();
+
+        // What did the server send us?
+        let body = res.into_body().collect().await?.to_bytes();
+
+        // Too bad we can't stream into a String :(
+        let body = String::from_utf8(body.to_vec()).expect("non utf-8 response from webdriver");
+
+        Ok(WireResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn shutdown(&mut self, ack: Option<Ack>, timeout: Option<Duration>) {
         // session was not created
         if self.session.is_none() {
             self.ongoing = Ongoing::Break;
             return;
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            session_id = self.session.as_deref(),
+            "deleting WebDriver session"
+        );
+
         let url = {
             self.wdb
                 .join(&format!("session/{}", self.session.as_ref().unwrap()))
                 .unwrap()
         };
 
+        let fut = self.exchange(WireRequest {
+            method: http::Method::DELETE,
+            headers: self.headers(&url),
+            url,
+            body: None,
+        });
         self.ongoing = Ongoing::Shutdown {
             ack,
-            fut: self.client.request(
-                hyper::Request::delete(url.as_str())
-                    .body(BoxBody::new(http_body_util::Empty::new()))
-                    .unwrap(),
-            ),
+            fut: Self::time_limited(fut, timeout),
         };
     }
 
@@ -639,54 +1070,123 @@ where
         client: hyper_util::client::legacy::Client<C, BoxBody<hyper::body::Bytes, Infallible>>,
         wdb: url::Url,
         session_id: Option<&str>,
+        config: Config,
     ) -> Result<Client, error::NewSessionError> {
+        let raw = match config.proxy {
+            Some(ref proxy) => {
+                crate::proxy::raw_client(proxy).map_err(error::NewSessionError::Lost)?
+            }
+            None => None,
+        };
+
         // We're going to need a channel for sending requests to the WebDriver host
         let (tx, rx) = mpsc::unbounded_channel();
 
         tokio::spawn(Session::new(
             rx,
             client,
+            raw,
             wdb,
             session_id.map(|id| id.to_string()),
+            config,
         ));
 
         // now that the session is running, let's do the handshake
         Ok(Client {
             tx,
             new_session_response: None,
+            timeout: None,
+            #[cfg(feature = "bidi")]
+            bidi: None,
         })
     }
 
     pub(crate) async fn with_capabilities_and_connector(
         webdriver: &str,
         cap: &webdriver::capabilities::Capabilities,
+        first_match: &[webdriver::capabilities::Capabilities],
         connector: C,
+        config: Config,
     ) -> Result<Client, error::NewSessionError> {
         let (client, wdb) = Self::create_client_and_parse_url(webdriver, connector).await?;
         let mut cap = cap.to_owned();
+        let mut alternatives = if first_match.is_empty() {
+            vec![webdriver::capabilities::Capabilities::new()]
+        } else {
+            first_match.to_vec()
+        };
 
         // Create a new session for this client
         // https://www.w3.org/TR/webdriver/#dfn-new-session
         // https://www.w3.org/TR/webdriver/#capabilities
+        //
+        // the remote end rejects the request if a key is in both alwaysMatch and a firstMatch
+        // entry, so defaults go into the alternatives if any of them already set the key.
+        //
         //  - we want the browser to wait for the page to load
         if !cap.contains_key("pageLoadStrategy") {
-            cap.insert("pageLoadStrategy".to_string(), Json::from("normal"));
+            if alternatives
+                .iter()
+                .any(|a| a.contains_key("pageLoadStrategy"))
+            {
+                for alternative in &mut alternatives {
+                    alternative
+                        .entry("pageLoadStrategy".to_string())
+                        .or_insert_with(|| Json::from("normal"));
+                }
+            } else {
+                cap.insert("pageLoadStrategy".to_string(), Json::from("normal"));
+            }
+        }
+
+        //  - we want the browser to use the same proxy as raw requests, unless told otherwise
+        match config.proxy {
+            Some(ref proxy) if !cap.contains_key("proxy") => {
+                let proxy =
+                    serde_json::to_value(proxy).expect("proxy configuration is always valid JSON");
+                if alternatives.iter().any(|a| a.contains_key("proxy")) {
+                    for alternative in &mut alternatives {
+                        alternative
+                            .entry("proxy".to_string())
+                            .or_insert_with(|| proxy.clone());
+                    }
+                } else {
+                    cap.insert("proxy".to_string(), proxy);
+                }
+            }
+            _ => {}
         }
 
         // make chrome comply with w3c
-        if cap.get("browserName") != Some(&Json::from("internet explorer")) {
-            cap.entry("goog:chromeOptions".to_string())
+        let is_ie = |c: &webdriver::capabilities::Capabilities| {
+            c.get("browserName") == Some(&Json::from("internet explorer"))
+        };
+        let set_w3c = |c: &mut webdriver::capabilities::Capabilities| {
+            c.entry("goog:chromeOptions".to_string())
                 .or_insert_with(|| Json::Object(serde_json::Map::new()))
                 .as_object_mut()
                 .expect("goog:chromeOptions wasn't a JSON object")
                 .insert("w3c".to_string(), Json::from(true));
+        };
+        if !is_ie(&cap) {
+            if cap.contains_key("goog:chromeOptions")
+                || alternatives
+                    .iter()
+                    .all(|a| !a.contains_key("goog:chromeOptions") && !is_ie(a))
+            {
+                set_w3c(&mut cap);
+            } else {
+                for alternative in alternatives.iter_mut().filter(|a| !is_ie(a)) {
+                    set_w3c(alternative);
+                }
+            }
         }
 
-        let mut client = Self::setup_session(client, wdb, None).await?;
+        let mut client = Self::setup_session(client, wdb, None, config).await?;
 
         let session_config = webdriver::capabilities::SpecNewSessionParameters {
-            alwaysMatch: cap.clone(),
-            firstMatch: vec![webdriver::capabilities::Capabilities::new()],
+            alwaysMatch: cap,
+            firstMatch: alternatives,
         };
         let spec = webdriver::command::NewSessionParameters {
             capabilities: session_config,
@@ -694,13 +1194,42 @@ where
 
         match Self::map_handshake_response(client.issue(WebDriverCommand::NewSession(spec)).await) {
             Ok(new_session_response) => {
-                client.new_session_response =
-                    Some(wd::NewSessionResponse::from_wd(new_session_response));
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    session_id = new_session_response.session_id.as_str(),
+                    "created WebDriver session"
+                );
+
+                client.new_session_response = Some(wd::NewSessionResponse::from_wd(
+                    new_session_response,
+                    first_match,
+                ));
+
+                // if the remote end handed us a WebDriver BiDi endpoint, connect to it as well
+                #[cfg(feature = "bidi")]
+                if let Some(Json::String(ws)) = client
+                    .capabilities()
+                    .and_then(|caps| caps.get("webSocketUrl"))
+                {
+                    match crate::bidi::BiDi::connect(ws).await {
+                        Ok(bidi) => client.bidi = Some(bidi),
+                        Err(e) => {
+                            // don't leave the freshly created session behind
+                            let _ = client.issue(Cmd::Shutdown).await;
+                            return Err(e);
+                        }
+                    }
+                }
+
                 Ok(client)
             }
             // the webdriver host _could_ still support the legacy webdriver protocol, but since
             // that's no longer supported by the webdriver crate, we also don't support it.
-            Err(e) => Err(e),
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %e, "failed to create WebDriver session");
+                Err(e)
+            }
         }
     }
 
@@ -713,134 +1242,231 @@ where
     /// [the spec]: https://www.w3.org/TR/webdriver/#list-of-endpoints
     fn issue_wd_cmd(
         &self,
-        cmd: Box<impl WebDriverCompatibleCommand + Send + 'static + ?Sized>,
+        cmd: Box<dyn WebDriverCompatibleCommand + Send>,
+        timeout: Option<Duration>,
     ) -> impl Future<Output = Result<Json, error::CmdError>> {
-        let input = cmd
-            .endpoint(&self.wdb, self.session.as_deref())
-            .map(|url| (url, self.ua.clone(), self.client.clone()));
+        let request = self.wire_request(&*cmd);
+        let send = self.command_sender(timeout);
+        let middleware = self.config.middleware.clone();
+
+        #[cfg(feature = "tracing")]
+        let fields = (
+            request
+                .as_ref()
+                .ok()
+                .map(|r| (r.method.clone(), r.url.path().to_string())),
+            self.session.clone(),
+        );
+
+        let fut = async move {
+            let request = CommandRequest {
+                command: cmd,
+                request: request?,
+            };
+            NextCommand::chain(middleware.into(), 0, send)
+                .run(request)
+                .await
+        };
 
-        async move {
-            let (url, ua, client) = input?;
+        #[cfg(feature = "tracing")]
+        let fut = async move {
+            let span = tracing::Span::current();
+            let (request, session_id) = fields;
+            if let Some((method, path)) = request {
+                span.record("method", method.as_str());
+                span.record("path", path.as_str());
+            }
+            if let Some(session_id) = session_id {
+                span.record("session_id", session_id.as_str());
+            }
 
-            let (method, mut body) = cmd.method_and_body(&url);
+            let start = std::time::Instant::now();
+            let res = fut.await;
+            span.record("duration_ms", start.elapsed().as_millis() as u64);
 
-            // issue the command to the webdriver server
-            let mut req = hyper::Request::builder();
-            req = req.method(method).uri(url.as_str());
-            if let Some(ref s) = ua {
-                req = req.header(hyper::header::USER_AGENT, s);
+            if let Err(ref e) = res {
+                span.record("error", tracing::field::display(e));
+                // categorize by the error the WebDriver server reported, if any
+                if let error::CmdError::Standard(ref wd) = e {
+                    span.record("error_status", wd.error.description());
+                }
+                tracing::debug!(error = %e, "WebDriver command failed");
             }
+            res
+        };
+
+        fut
+    }
+
+    /// The headers to send along with every request to the WebDriver server at `url`.
+    fn headers(&self, url: &url::Url) -> http::HeaderMap {
+        let mut headers = self.config.headers.clone();
+        if let Some(ref s) = self.ua {
+            if let Ok(ua) = http::HeaderValue::from_str(s) {
+                headers.insert(hyper::header::USER_AGENT, ua);
+            }
+        }
+        if let Some(ref auth) = self.config.auth {
+            headers.insert(hyper::header::AUTHORIZATION, auth.clone());
+        } else if !url.username().is_empty() || url.password().is_some() {
             // because https://github.com/hyperium/hyper/pull/727
-            if !url.username().is_empty() || url.password().is_some() {
-                req = req.header(
-                    hyper::header::AUTHORIZATION,
-                    format!(
-                        "Basic {}",
-                        base64::engine::general_purpose::STANDARD.encode(&format!(
-                            "{}:{}",
-                            url.username(),
-                            url.password().unwrap_or("")
-                        ))
-                    ),
-                );
+            let auth = basic_auth(url.username(), url.password().unwrap_or(""));
+            headers.insert(hyper::header::AUTHORIZATION, auth);
+        }
+        headers
+    }
+
+    /// Compute the HTTP request for a WebDriver command.
+    fn wire_request(
+        &self,
+        cmd: &(dyn WebDriverCompatibleCommand + Send),
+    ) -> Result<WireRequest, error::CmdError> {
+        let url = cmd.endpoint(&self.wdb, self.session.as_deref())?;
+        let (method, body) = cmd.method_and_body(&url);
+
+        let mut headers = self.headers(&url);
+
+        let json_mime: mime::Mime = "application/json; charset=utf-8"
+            .parse::<mime::Mime>()
+            .unwrap_or(mime::APPLICATION_JSON);
+
+        if body.is_some() {
+            if let Ok(ctype) = http::HeaderValue::from_str(json_mime.as_ref()) {
+                headers.insert(hyper::header::CONTENT_TYPE, ctype);
             }
+        }
 
-            let json_mime: mime::Mime = "application/json; charset=utf-8"
-                .parse::<mime::Mime>()
-                .unwrap_or(mime::APPLICATION_JSON);
+        Ok(WireRequest {
+            method,
+            url,
+            headers,
+            body,
+        })
+    }
 
-            let body = if let Some(body) = body.take() {
-                req = req.header(hyper::header::CONTENT_TYPE, json_mime.as_ref());
-                req = req.header(hyper::header::CONTENT_LENGTH, body.len());
-                req.body(BoxBody::new(body)).unwrap()
-            } else {
-                req.body(BoxBody::new(http_body_util::Empty::new()))
-                    .unwrap()
-            };
+    /// The innermost layer of the middleware stack, which sends the request to the server.
+    fn command_sender(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Arc<dyn Fn(CommandRequest) -> CommandFuture + Send + Sync> {
+        let exchange = self.exchanger();
+        let retry = self.config.retry;
+        Arc::new(move |CommandRequest { command, request }| {
+            let idempotent = command.is_idempotent();
+            let exchange = exchange.clone();
+            Box::pin(async move {
+                // issue the command to the webdriver server
+                let mut attempt = 1;
+                let res = loop {
+                    match Self::time_limited(exchange(request.clone()), timeout).await {
+                        Err(e) => {
+                            attempt += 1;
+                            match retry.and_then(|r| r.retry_after(&e, attempt, idempotent)) {
+                                Some(backoff) => tokio::time::sleep(backoff).await,
+                                None => return Err(e),
+                            }
+                        }
+                        Ok(res) => break res,
+                    }
+                };
+                Self::parse_response(res)
+            })
+        })
+    }
 
-            let res = client.request(body).await?;
-            // keep track of result status (.body() consumes self -- ugh)
-            let status = res.status();
+    /// Fail `fut` with [`error::CmdError::RequestTimeout`] if it does not complete within `timeout`.
+    fn time_limited(fut: TransportFuture, timeout: Option<Duration>) -> TransportFuture {
+        match timeout {
+            Some(timeout) => Box::pin(async move {
+                tokio::time::timeout(timeout, fut)
+                    .await
+                    .unwrap_or(Err(error::CmdError::RequestTimeout(timeout)))
+            }),
+            None => fut,
+        }
+    }
 
-            // check that the server sent us json
-            let ctype = res
-                .headers()
-                .get(hyper::header::CONTENT_TYPE)
-                .and_then(|ctype| ctype.to_str().ok()?.parse::<mime::Mime>().ok());
+    /// Parse the response to a WebDriver command.
+    fn parse_response(res: WireResponse) -> Result<Json, error::CmdError> {
+        // keep track of result status
+        let status = res.status;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("status", status.as_u16());
 
-            // What did the server send us?
-            let body = res.into_body().collect().await?.to_bytes();
+        // check that the server sent us json
+        let ctype = res
+            .headers
+            .get(hyper::header::CONTENT_TYPE)
+            .and_then(|ctype| ctype.to_str().ok()?.parse::<mime::Mime>().ok());
 
-            // Too bad we can't stream into a String :(
-            let body = String::from_utf8(body.to_vec()).expect("non utf-8 response from webdriver");
+        let body = res.body;
 
-            if let Some(ctype) = ctype {
-                if ctype.type_() != mime::APPLICATION_JSON.type_()
-                    || ctype.subtype() != mime::APPLICATION_JSON.subtype()
-                {
-                    // nope, something else...
-                    return Err(error::CmdError::NotJson(body));
-                }
-            } else {
-                // WebDriver host sent us something weird...
+        if let Some(ctype) = ctype {
+            if ctype.type_() != mime::APPLICATION_JSON.type_()
+                || ctype.subtype() != mime::APPLICATION_JSON.subtype()
+            {
+                // nope, something else...
                 return Err(error::CmdError::NotJson(body));
             }
+        } else {
+            // WebDriver host sent us something weird...
+            return Err(error::CmdError::NotJson(body));
+        }
 
-            let is_success = status.is_success();
+        let is_success = status.is_success();
 
-            // https://www.w3.org/TR/webdriver/#dfn-send-a-response
-            // NOTE: the standard specifies that even errors use the "Send a Response" steps
-            let body = match serde_json::from_str(&body)? {
-                Json::Object(mut v) => v
-                    .remove("value")
-                    .ok_or(error::CmdError::NotW3C(Json::Object(v))),
-                v => Err(error::CmdError::NotW3C(v)),
-            }?;
+        // https://www.w3.org/TR/webdriver/#dfn-send-a-response
+        // NOTE: the standard specifies that even errors use the "Send a Response" steps
+        let body = match serde_json::from_str(&body)? {
+            Json::Object(mut v) => v
+                .remove("value")
+                .ok_or(error::CmdError::NotW3C(Json::Object(v))),
+            v => Err(error::CmdError::NotW3C(v)),
+        }?;
 
-            if is_success {
-                return Ok(body);
-            }
+        if is_success {
+            return Ok(body);
+        }
 
-            // https://www.w3.org/TR/webdriver/#dfn-send-an-error
-            // https://www.w3.org/TR/webdriver/#handling-errors
-            let mut body = match body {
-                Json::Object(o) => o,
-                j => return Err(error::CmdError::NotW3C(j)),
-            };
+        // https://www.w3.org/TR/webdriver/#dfn-send-an-error
+        // https://www.w3.org/TR/webdriver/#handling-errors
+        let mut body = match body {
+            Json::Object(o) => o,
+            j => return Err(error::CmdError::NotW3C(j)),
+        };
 
-            // phantomjs injects a *huge* field with the entire screen contents -- remove that
-            body.remove("screen");
+        // phantomjs injects a *huge* field with the entire screen contents -- remove that
+        body.remove("screen");
 
-            if !body.contains_key("error")
-                || !body.contains_key("message")
-                || !body["error"].is_string()
-                || !body["message"].is_string()
-            {
-                return Err(error::CmdError::NotW3C(Json::Object(body)));
-            }
+        if !body.contains_key("error")
+            || !body.contains_key("message")
+            || !body["error"].is_string()
+            || !body["message"].is_string()
+        {
+            return Err(error::CmdError::NotW3C(Json::Object(body)));
+        }
 
-            let Some(es) = body["error"].as_str() else {
-                return Err(error::CmdError::NotW3C(Json::Object(body)));
-            };
-            let es = es.parse()?;
+        let Some(es) = body["error"].as_str() else {
+            return Err(error::CmdError::NotW3C(Json::Object(body)));
+        };
+        let es = es.parse()?;
 
-            let message = match body.remove("message") {
-                Some(Json::String(x)) => x,
-                _ => String::new(),
-            };
+        let message = match body.remove("message") {
+            Some(Json::String(x)) => x,
+            _ => String::new(),
+        };
 
-            let mut wd_error = error::WebDriver::new(es, message);
+        let mut wd_error = error::WebDriver::new(es, message);
 
-            // Add the stacktrace if there is one.
-            if let Some(Json::String(x)) = body.remove("stacktrace") {
-                wd_error = wd_error.with_stacktrace(x);
-            }
+        // Add the stacktrace if there is one.
+        if let Some(Json::String(x)) = body.remove("stacktrace") {
+            wd_error = wd_error.with_stacktrace(x);
+        }
 
-            // Some commands may annotate errors with extra data.
-            if let Some(x) = body.remove("data") {
-                wd_error = wd_error.with_data(x);
-            }
-            Err(error::CmdError::from_webdriver_error(wd_error))
+        // Some commands may annotate errors with extra data.
+        if let Some(x) = body.remove("data") {
+            wd_error = wd_error.with_data(x);
         }
+        Err(error::CmdError::from_webdriver_error(wd_error))
     }
 }
@@ -0,0 +1,1228 @@
+//! An in-process stand-in for a WebDriver server, for testing code built on fantoccini.
+//!
+//! Driving a real browser in unit tests is slow, and requires a WebDriver process and browser to
+//! be installed wherever the tests run. [`MockWebDriver`] instead serves the [W3C WebDriver
+//! protocol][1] on a local port, and answers commands from a small programmable fake DOM or from
+//! canned responses. Every command it receives is recorded, so that tests can assert on what was
+//! sent.
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use fantoccini::testing::{MockElement, MockWebDriver};
+//! use fantoccini::{ClientBuilder, Locator};
+//!
+//! let server = MockWebDriver::start().await?;
+//! server.set_dom(vec![MockElement::new("div")
+//!     .id("greeting")
+//!     .child(MockElement::new("span").class("name").text("world"))]);
+//!
+//! # #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+//! let c = ClientBuilder::native().connect(&server.url()).await?;
+//! # #[cfg(feature = "rustls-tls")]
+//! # let c = ClientBuilder::rustls()?.connect(&server.url()).await?;
+//! # #[cfg(all(not(feature = "native-tls"), not(feature = "rustls-tls")))]
+//! # let c: fantoccini::Client = unreachable!("no tls provider available");
+//! let name = c.find(Locator::Css("#greeting .name")).await?;
+//! assert_eq!(name.text().await?, "world");
+//! c.close().await?;
+//!
+//! assert!(server.received().iter().any(|cmd| cmd.path.ends_with("/element")));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The fake DOM understands simple CSS selectors (tag names, `#id`, `.class` and attribute
+//! selectors, combined with descendant and child combinators), the XPath expressions that
+//! [`Locator`](crate::Locator) generates, and link text. Anything beyond that is best served
+//! through [`MockWebDriver::respond`].
+//!
+//! [1]: https://www.w3.org/TR/webdriver/
+
+use crate::error::ErrorStatus;
+use base64::Engine;
+use http::{Method, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value as Json};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use webdriver::common::ELEMENT_KEY;
+
+/// A WebDriver command received by a [`MockWebDriver`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedCommand {
+    /// The HTTP method of the request.
+    pub method: Method,
+    /// The request path, such as `/session/mock-session-1/url`.
+    pub path: String,
+    /// The headers of the request.
+    pub headers: http::HeaderMap,
+    /// The JSON request body, if there was one.
+    pub body: Option<Json>,
+}
+
+/// A canned reply for [`MockWebDriver::respond`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    status: StatusCode,
+    value: Json,
+}
+
+impl MockResponse {
+    /// Reply successfully with the given `value`.
+    pub fn ok(value: Json) -> Self {
+        MockResponse {
+            status: StatusCode::OK,
+            value,
+        }
+    }
+
+    /// Reply with the given WebDriver error.
+    pub fn error(error: ErrorStatus, message: &str) -> Self {
+        MockResponse {
+            status: error.http_status(),
+            value: json!({
+                "error": error.description(),
+                "message": message,
+                "stacktrace": "",
+            }),
+        }
+    }
+}
+
+/// An element in the fake DOM of a [`MockWebDriver`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MockElement {
+    tag: String,
+    attributes: BTreeMap<String, String>,
+    text: String,
+    children: Vec<MockElement>,
+    displayed: bool,
+    enabled: bool,
+    selected: bool,
+    rect: (f64, f64, f64, f64),
+}
+
+impl MockElement {
+    /// Create a new element with the given tag name.
+    ///
+    /// The element starts out displayed, enabled, and not selected.
+    pub fn new(tag: &str) -> Self {
+        MockElement {
+            tag: tag.to_ascii_lowercase(),
+            displayed: true,
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Set the `id` attribute of the element.
+    #[must_use]
+    pub fn id(self, id: &str) -> Self {
+        self.attr("id", id)
+    }
+
+    /// Add a class to the `class` attribute of the element.
+    #[must_use]
+    pub fn class(mut self, class: &str) -> Self {
+        let classes = self.attributes.entry("class".to_string()).or_default();
+        if !classes.is_empty() {
+            classes.push(' ');
+        }
+        classes.push_str(class);
+        self
+    }
+
+    /// Set an attribute of the element.
+    #[must_use]
+    pub fn attr(mut self, name: &str, value: &str) -> Self {
+        self.attributes.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the text content of the element, which precedes that of its children.
+    #[must_use]
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+
+    /// Append a child element.
+    #[must_use]
+    pub fn child(mut self, child: MockElement) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Mark the element as not displayed.
+    #[must_use]
+    pub fn hidden(mut self) -> Self {
+        self.displayed = false;
+        self
+    }
+
+    /// Mark the element as disabled.
+    #[must_use]
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    /// Mark the element as selected (or checked).
+    #[must_use]
+    pub fn selected(mut self) -> Self {
+        self.selected = true;
+        self
+    }
+
+    /// Set the bounding rectangle of the element.
+    #[must_use]
+    pub fn rect(mut self, x: f64, y: f64, width: f64, height: f64) -> Self {
+        self.rect = (x, y, width, height);
+        self
+    }
+}
+
+/// A flattened [`MockElement`], addressable by its index in [`State::nodes`].
+#[derive(Debug)]
+struct Node {
+    tag: String,
+    attributes: BTreeMap<String, String>,
+    text: String,
+    children: Vec<usize>,
+    parent: Option<usize>,
+    displayed: bool,
+    enabled: bool,
+    selected: bool,
+    rect: (f64, f64, f64, f64),
+}
+
+#[derive(Debug)]
+struct Session {
+    history: Vec<String>,
+    position: usize,
+    windows: Vec<String>,
+    window: String,
+    cookies: Vec<serde_json::Map<String, Json>>,
+    timeouts: serde_json::Map<String, Json>,
+}
+
+impl Session {
+    fn url(&self) -> &str {
+        &self.history[self.position]
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    received: Vec<ReceivedCommand>,
+    canned: Vec<(Method, String, MockResponse)>,
+    scripts: Vec<(String, Json)>,
+    capabilities: serde_json::Map<String, Json>,
+    sessions: HashMap<String, Session>,
+    next_id: usize,
+    title: String,
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    latency: Duration,
+    drop_next: usize,
+}
+
+/// A scriptable W3C WebDriver server running on a local port.
+///
+/// The server runs on the tokio runtime that [`start`](MockWebDriver::start) was called from, and
+/// stops when the `MockWebDriver` is dropped.
+#[derive(Debug)]
+pub struct MockWebDriver {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl Drop for MockWebDriver {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl MockWebDriver {
+    /// Start a new server on a free port on the loopback interface.
+    pub async fn start() -> io::Result<Self> {
+        Self::start_on(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await
+    }
+
+    /// Start a new server listening on the given address.
+    pub async fn start_on(addr: SocketAddr) -> io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+
+        let mut capabilities = serde_json::Map::new();
+        capabilities.insert("browserName".to_string(), json!("mock"));
+        capabilities.insert("browserVersion".to_string(), json!("1.0"));
+        capabilities.insert("platformName".to_string(), json!("mock"));
+        let state = Arc::new(Mutex::new(State {
+            capabilities,
+            ..Default::default()
+        }));
+
+        let s = Arc::clone(&state);
+        let server = tokio::spawn(async move {
+            loop {
+                let (conn, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(_) => continue,
+                };
+                let s = Arc::clone(&s);
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(Arc::clone(&s), req));
+                    let _ = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(conn), service)
+                        .await;
+                });
+            }
+        });
+
+        Ok(MockWebDriver {
+            addr,
+            state,
+            server,
+        })
+    }
+
+    /// The URL to pass to [`ClientBuilder::connect`](crate::ClientBuilder::connect).
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// The address the server is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// All commands received so far, in order.
+    pub fn received(&self) -> Vec<ReceivedCommand> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Forget all commands received so far.
+    pub fn clear_received(&self) {
+        self.state.lock().unwrap().received.clear();
+    }
+
+    /// Replace the fake DOM with the given top-level elements.
+    ///
+    /// References to elements of the previous DOM become stale.
+    pub fn set_dom(&self, elements: Vec<MockElement>) {
+        let mut state = self.state.lock().unwrap();
+        state.nodes.clear();
+        state.roots.clear();
+        for element in elements {
+            let root = state.insert(element, None);
+            state.roots.push(root);
+        }
+    }
+
+    /// Set the title reported for every page.
+    pub fn set_title(&self, title: &str) {
+        self.state.lock().unwrap().title = title.to_string();
+    }
+
+    /// Close the connection instead of responding to the next `n` requests.
+    ///
+    /// This simulates a flaky connection to the WebDriver server. The requests are still recorded.
+    pub fn drop_next(&self, n: usize) {
+        self.state.lock().unwrap().drop_next = n;
+    }
+
+    /// Delay the handling of every request by `latency`, to simulate a slow remote end.
+    ///
+    /// Requests are handled concurrently, so this does not serialize them.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Set a capability that is returned when a new session is created.
+    pub fn set_capability(&self, name: &str, value: Json) {
+        let mut state = self.state.lock().unwrap();
+        state.capabilities.insert(name.to_string(), value);
+    }
+
+    /// Reply to requests for `method` and `path` with the given response.
+    ///
+    /// `path` is either relative to the session (such as `"title"` or `"element/*/text"`), or
+    /// absolute (such as `"/status"`). A `*` matches a single path segment. Canned responses take
+    /// precedence over the fake DOM, and the most recently added matching response wins.
+    pub fn respond(&self, method: Method, path: &str, response: MockResponse) {
+        let mut state = self.state.lock().unwrap();
+        state.canned.push((method, path.to_string(), response));
+    }
+
+    /// Make scripts that contain `snippet` return `result` when executed.
+    ///
+    /// Scripts that do not match any snippet return `null`.
+    pub fn on_script(&self, snippet: &str, result: Json) {
+        let mut state = self.state.lock().unwrap();
+        state.scripts.push((snippet.to_string(), result));
+    }
+
+    /// The ids of all sessions that are currently open.
+    pub fn sessions(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut ids: Vec<_> = state.sessions.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// End the session with the given id, as if it had timed out on the server.
+    ///
+    /// Further commands for the session fail with [`ErrorStatus::InvalidSessionId`].
+    pub fn expire_session(&self, id: &str) {
+        self.state.lock().unwrap().sessions.remove(id);
+    }
+}
+
+fn respond(status: StatusCode, value: Json) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/json; charset=utf-8",
+        )
+        .body(Full::new(Bytes::from(
+            json!({ "value": value }).to_string(),
+        )))
+        .unwrap()
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, io::Error> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let headers = req.headers().clone();
+    let body = match req.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => Bytes::new(),
+    };
+    let body = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(body) => Some(body),
+            Err(e) => {
+                let rsp = MockResponse::error(ErrorStatus::InvalidArgument, &e.to_string());
+                return Ok(respond(rsp.status, rsp.value));
+            }
+        }
+    };
+
+    let latency = {
+        let mut state = state.lock().unwrap();
+        state.received.push(ReceivedCommand {
+            method: method.clone(),
+            path: path.clone(),
+            headers,
+            body: body.clone(),
+        });
+        if state.drop_next > 0 {
+            state.drop_next -= 1;
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "dropping connection as instructed",
+            ));
+        }
+        state.latency
+    };
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+
+    let mut state = state.lock().unwrap();
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let rsp = match state.canned(&method, &segments) {
+        Some(rsp) => rsp,
+        None => state
+            .dispatch(&method, &segments, body.unwrap_or(Json::Null))
+            .unwrap_or_else(|e| e),
+    };
+    Ok(respond(rsp.status, rsp.value))
+}
+
+fn no_such_element() -> MockResponse {
+    MockResponse::error(ErrorStatus::NoSuchElement, "no such element")
+}
+
+fn invalid_argument(msg: &str) -> MockResponse {
+    MockResponse::error(ErrorStatus::InvalidArgument, msg)
+}
+
+fn null() -> Result<MockResponse, MockResponse> {
+    Ok(MockResponse::ok(Json::Null))
+}
+
+fn string_param(body: &Json, key: &str) -> Result<String, MockResponse> {
+    body.get(key)
+        .and_then(Json::as_str)
+        .map(String::from)
+        .ok_or_else(|| invalid_argument(&format!("missing string parameter `{}`", key)))
+}
+
+fn element_ref(id: usize) -> Json {
+    json!({ ELEMENT_KEY: format!("mock-element-{}", id) })
+}
+
+impl State {
+    fn insert(&mut self, element: MockElement, parent: Option<usize>) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            tag: element.tag,
+            attributes: element.attributes,
+            text: element.text,
+            children: Vec::new(),
+            parent,
+            displayed: element.displayed,
+            enabled: element.enabled,
+            selected: element.selected,
+            rect: element.rect,
+        });
+        for child in element.children {
+            let child = self.insert(child, Some(id));
+            self.nodes[id].children.push(child);
+        }
+        id
+    }
+
+    fn canned(&self, method: &Method, segments: &[&str]) -> Option<MockResponse> {
+        // paths without a leading / are relative to /session/<id>/
+        let relative = match segments {
+            ["session", _, rest @ ..] => Some(rest),
+            _ => None,
+        };
+        self.canned
+            .iter()
+            .rev()
+            .find(|(m, pattern, _)| {
+                if m != method {
+                    return false;
+                }
+                let (pattern, against) = match pattern.strip_prefix('/') {
+                    Some(pattern) => (pattern, segments),
+                    None => match relative {
+                        Some(rest) => (pattern.as_str(), rest),
+                        None => return false,
+                    },
+                };
+                let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+                pattern.len() == against.len()
+                    && pattern
+                        .iter()
+                        .zip(against)
+                        .all(|(p, s)| *p == "*" || p == s)
+            })
+            .map(|(_, _, rsp)| rsp.clone())
+    }
+
+    fn dispatch(
+        &mut self,
+        method: &Method,
+        segments: &[&str],
+        body: Json,
+    ) -> Result<MockResponse, MockResponse> {
+        let (sid, rest) = match segments {
+            ["status"] if method == Method::GET => {
+                return Ok(MockResponse::ok(
+                    json!({ "ready": true, "message": "mock WebDriver ready" }),
+                ))
+            }
+            ["session"] if method == Method::POST => return Ok(self.new_session()),
+            ["session", sid] if method == Method::DELETE => {
+                self.sessions.remove(*sid);
+                return null();
+            }
+            ["session", sid, rest @ ..] => (*sid, rest),
+            _ => {
+                return Err(MockResponse::error(
+                    ErrorStatus::UnknownCommand,
+                    "unknown command",
+                ))
+            }
+        };
+
+        if !self.sessions.contains_key(sid) {
+            return Err(MockResponse::error(
+                ErrorStatus::InvalidSessionId,
+                "invalid session id",
+            ));
+        }
+
+        match (method.clone(), rest) {
+            (Method::GET, ["url"]) => Ok(MockResponse::ok(json!(self.session(sid).url()))),
+            (Method::POST, ["url"]) => {
+                let url = string_param(&body, "url")?;
+                self.navigate(sid, url);
+                null()
+            }
+            (Method::POST, ["back"]) => {
+                let session = self.session(sid);
+                session.position = session.position.saturating_sub(1);
+                null()
+            }
+            (Method::POST, ["forward"]) => {
+                let session = self.session(sid);
+                if session.position + 1 < session.history.len() {
+                    session.position += 1;
+                }
+                null()
+            }
+            (Method::POST, ["refresh"]) => null(),
+            (Method::GET, ["title"]) => Ok(MockResponse::ok(json!(self.title))),
+            (Method::GET, ["source"]) => {
+                let html: String = self.roots.iter().map(|&id| self.html(id, true)).collect();
+                Ok(MockResponse::ok(json!(format!(
+                    "<html><head></head><body>{}</body></html>",
+                    html
+                ))))
+            }
+            (Method::GET, ["timeouts"]) => Ok(MockResponse::ok(Json::Object(
+                self.session(sid).timeouts.clone(),
+            ))),
+            (Method::POST, ["timeouts"]) => {
+                if let Json::Object(timeouts) = body {
+                    self.session(sid).timeouts.extend(timeouts);
+                }
+                null()
+            }
+            (Method::GET, ["window"]) => Ok(MockResponse::ok(json!(self.session(sid).window))),
+            (Method::POST, ["window"]) => {
+                let handle = string_param(&body, "handle")?;
+                let session = self.session(sid);
+                if !session.windows.contains(&handle) {
+                    return Err(MockResponse::error(
+                        ErrorStatus::NoSuchWindow,
+                        "no such window",
+                    ));
+                }
+                session.window = handle;
+                null()
+            }
+            (Method::DELETE, ["window"]) => {
+                let session = self.session(sid);
+                let current = session.window.clone();
+                session.windows.retain(|w| *w != current);
+                let windows = json!(session.windows);
+                if session.windows.is_empty() {
+                    self.sessions.remove(sid);
+                }
+                Ok(MockResponse::ok(windows))
+            }
+            (Method::GET, ["window", "handles"]) => {
+                Ok(MockResponse::ok(json!(self.session(sid).windows)))
+            }
+            (Method::POST, ["window", "new"]) => {
+                let handle = self.next_id("mock-window");
+                self.session(sid).windows.push(handle.clone());
+                let typ = body.get("type").and_then(Json::as_str).unwrap_or("tab");
+                Ok(MockResponse::ok(json!({ "handle": handle, "type": typ })))
+            }
+            (Method::GET, ["window", "rect"]) => Ok(MockResponse::ok(
+                json!({ "x": 0, "y": 0, "width": 1280, "height": 720 }),
+            )),
+            (Method::POST, ["window", _]) | (Method::POST, ["frame"]) => null(),
+            (Method::POST, ["frame", "parent"]) => null(),
+            (Method::POST, ["element"]) => {
+                let found = self.find(None, &body)?;
+                found
+                    .first()
+                    .map(|&id| MockResponse::ok(element_ref(id)))
+                    .ok_or_else(no_such_element)
+            }
+            (Method::POST, ["elements"]) => {
+                let found = self.find(None, &body)?;
+                Ok(MockResponse::ok(Json::Array(
+                    found.into_iter().map(element_ref).collect(),
+                )))
+            }
+            (Method::GET, ["element", "active"]) => Err(no_such_element()),
+            (method, ["element", eid, rest @ ..]) => {
+                let id = self.node(eid)?;
+                self.element_command(sid, id, method, rest, body)
+            }
+            (Method::POST, ["execute", "sync"]) | (Method::POST, ["execute", "async"]) => {
+                let script = string_param(&body, "script")?;
+                let result = self
+                    .scripts
+                    .iter()
+                    .rev()
+                    .find(|(snippet, _)| script.contains(snippet.as_str()))
+                    .map(|(_, result)| result.clone())
+                    .unwrap_or(Json::Null);
+                Ok(MockResponse::ok(result))
+            }
+            (Method::GET, ["cookie"]) => {
+                let cookies = self.session(sid).cookies.clone();
+                Ok(MockResponse::ok(json!(cookies)))
+            }
+            (Method::POST, ["cookie"]) => {
+                let cookie = match body.get("cookie") {
+                    Some(Json::Object(cookie)) => cookie.clone(),
+                    _ => return Err(invalid_argument("missing parameter `cookie`")),
+                };
+                let cookies = &mut self.session(sid).cookies;
+                cookies.retain(|c| c.get("name") != cookie.get("name"));
+                cookies.push(cookie);
+                null()
+            }
+            (Method::DELETE, ["cookie"]) => {
+                self.session(sid).cookies.clear();
+                null()
+            }
+            (Method::GET, ["cookie", name]) => self
+                .session(sid)
+                .cookies
+                .iter()
+                .find(|c| c.get("name").and_then(Json::as_str) == Some(*name))
+                .map(|c| MockResponse::ok(Json::Object(c.clone())))
+                .ok_or_else(|| MockResponse::error(ErrorStatus::NoSuchCookie, "no such cookie")),
+            (Method::DELETE, ["cookie", name]) => {
+                self.session(sid)
+                    .cookies
+                    .retain(|c| c.get("name").and_then(Json::as_str) != Some(*name));
+                null()
+            }
+            (Method::POST, ["actions"]) | (Method::DELETE, ["actions"]) => null(),
+            (_, ["alert", ..]) => Err(MockResponse::error(
+                ErrorStatus::NoSuchAlert,
+                "no such alert",
+            )),
+            (Method::GET, ["screenshot"]) => Ok(MockResponse::ok(json!(screenshot()))),
+            (Method::POST, ["print"]) => Ok(MockResponse::ok(json!(
+                base64::engine::general_purpose::STANDARD.encode(b"%PDF-1.4")
+            ))),
+            _ => Err(MockResponse::error(
+                ErrorStatus::UnknownCommand,
+                "unknown command",
+            )),
+        }
+    }
+
+    fn element_command(
+        &mut self,
+        sid: &str,
+        id: usize,
+        method: Method,
+        rest: &[&str],
+        body: Json,
+    ) -> Result<MockResponse, MockResponse> {
+        match (method, rest) {
+            (Method::POST, ["element"]) => {
+                let found = self.find(Some(id), &body)?;
+                found
+                    .first()
+                    .map(|&id| MockResponse::ok(element_ref(id)))
+                    .ok_or_else(no_such_element)
+            }
+            (Method::POST, ["elements"]) => {
+                let found = self.find(Some(id), &body)?;
+                Ok(MockResponse::ok(Json::Array(
+                    found.into_iter().map(element_ref).collect(),
+                )))
+            }
+            (Method::GET, ["text"]) => Ok(MockResponse::ok(json!(self.text(id)))),
+            (Method::GET, ["name"]) => Ok(MockResponse::ok(json!(self.nodes[id].tag))),
+            (Method::GET, ["attribute", name]) => Ok(MockResponse::ok(json!(self.nodes[id]
+                .attributes
+                .get(*name)))),
+            (Method::GET, ["property", name]) => {
+                let node = &self.nodes[id];
+                let value = match *name {
+                    "innerHTML" => json!(self.html(id, false)),
+                    "outerHTML" => json!(self.html(id, true)),
+                    "textContent" | "innerText" => json!(self.text(id)),
+                    "tagName" => json!(node.tag.to_ascii_uppercase()),
+                    "checked" | "selected" => json!(node.selected),
+                    "disabled" => json!(!node.enabled),
+                    name => json!(node.attributes.get(name)),
+                };
+                Ok(MockResponse::ok(value))
+            }
+            (Method::GET, ["css", _]) => Ok(MockResponse::ok(json!(""))),
+            (Method::GET, ["rect"]) => {
+                let (x, y, width, height) = self.nodes[id].rect;
+                Ok(MockResponse::ok(
+                    json!({ "x": x, "y": y, "width": width, "height": height }),
+                ))
+            }
+            (Method::GET, ["displayed"]) => Ok(MockResponse::ok(json!(self.nodes[id].displayed))),
+            (Method::GET, ["enabled"]) => Ok(MockResponse::ok(json!(self.nodes[id].enabled))),
+            (Method::GET, ["selected"]) => Ok(MockResponse::ok(json!(self.nodes[id].selected))),
+            (Method::GET, ["shadow"]) => Err(MockResponse::error(
+                ErrorStatus::NoSuchShadowRoot,
+                "no such shadow root",
+            )),
+            (Method::GET, ["screenshot"]) => Ok(MockResponse::ok(json!(screenshot()))),
+            (Method::POST, ["click"]) => {
+                let node = &mut self.nodes[id];
+                if !node.displayed || !node.enabled {
+                    return Err(MockResponse::error(
+                        ErrorStatus::ElementNotInteractable,
+                        "element not interactable",
+                    ));
+                }
+                match node.attributes.get("type").map(String::as_str) {
+                    Some("checkbox") => node.selected = !node.selected,
+                    Some("radio") => node.selected = true,
+                    _ if node.tag == "option" => node.selected = true,
+                    _ => {}
+                }
+                if let Some(href) = node.attributes.get("href").cloned() {
+                    let base = self.session(sid).url().to_string();
+                    let href = url::Url::parse(&base)
+                        .and_then(|base| base.join(&href))
+                        .map(String::from)
+                        .unwrap_or(href);
+                    self.navigate(sid, href);
+                }
+                null()
+            }
+            (Method::POST, ["clear"]) => {
+                self.nodes[id]
+                    .attributes
+                    .insert("value".to_string(), String::new());
+                null()
+            }
+            (Method::POST, ["value"]) => {
+                let text = string_param(&body, "text")?;
+                self.nodes[id]
+                    .attributes
+                    .entry("value".to_string())
+                    .or_default()
+                    .push_str(&text);
+                null()
+            }
+            _ => Err(MockResponse::error(
+                ErrorStatus::UnknownCommand,
+                "unknown command",
+            )),
+        }
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}-{}", prefix, self.next_id)
+    }
+
+    fn new_session(&mut self) -> MockResponse {
+        let sid = self.next_id("mock-session");
+        let window = self.next_id("mock-window");
+        let timeouts = json!({ "script": 30000, "pageLoad": 300000, "implicit": 0 });
+        let Json::Object(timeouts) = timeouts else {
+            unreachable!()
+        };
+        self.sessions.insert(
+            sid.clone(),
+            Session {
+                history: vec!["about:blank".to_string()],
+                position: 0,
+                windows: vec![window.clone()],
+                window,
+                cookies: Vec::new(),
+                timeouts,
+            },
+        );
+        MockResponse::ok(json!({
+            "sessionId": sid,
+            "capabilities": self.capabilities,
+        }))
+    }
+
+    fn session(&mut self, sid: &str) -> &mut Session {
+        self.sessions
+            .get_mut(sid)
+            .expect("session existence is checked in dispatch")
+    }
+
+    fn navigate(&mut self, sid: &str, url: String) {
+        let session = self.session(sid);
+        session.history.truncate(session.position + 1);
+        session.history.push(url);
+        session.position += 1;
+    }
+
+    fn node(&self, eid: &str) -> Result<usize, MockResponse> {
+        eid.strip_prefix("mock-element-")
+            .and_then(|id| id.parse().ok())
+            .filter(|&id| id < self.nodes.len())
+            .ok_or_else(|| {
+                MockResponse::error(
+                    ErrorStatus::StaleElementReference,
+                    "stale element reference",
+                )
+            })
+    }
+
+    fn text(&self, id: usize) -> String {
+        let node = &self.nodes[id];
+        let mut text = node.text.clone();
+        for &child in &node.children {
+            text.push_str(&self.text(child));
+        }
+        text
+    }
+
+    fn html(&self, id: usize, outer: bool) -> String {
+        let node = &self.nodes[id];
+        let mut html = node.text.clone();
+        for &child in &node.children {
+            html.push_str(&self.html(child, true));
+        }
+        if !outer {
+            return html;
+        }
+        let attributes: String = node
+            .attributes
+            .iter()
+            .map(|(k, v)| format!(" {}=\"{}\"", k, v.replace('"', "&quot;")))
+            .collect();
+        format!("<{0}{1}>{2}</{0}>", node.tag, attributes, html)
+    }
+
+    /// All nodes below `root` (or in the whole document), in document order.
+    fn descendants(&self, root: Option<usize>) -> Vec<usize> {
+        fn walk(nodes: &[Node], id: usize, out: &mut Vec<usize>) {
+            out.push(id);
+            for &child in &nodes[id].children {
+                walk(nodes, child, out);
+            }
+        }
+        let mut out = Vec::new();
+        match root {
+            Some(root) => {
+                for &child in &self.nodes[root].children {
+                    walk(&self.nodes, child, &mut out);
+                }
+            }
+            None => {
+                for &root in &self.roots {
+                    walk(&self.nodes, root, &mut out);
+                }
+            }
+        }
+        out
+    }
+
+    fn find(&self, root: Option<usize>, body: &Json) -> Result<Vec<usize>, MockResponse> {
+        let using = string_param(body, "using")?;
+        let value = string_param(body, "value")?;
+        let candidates = self.descendants(root);
+        let invalid = || MockResponse::error(ErrorStatus::InvalidSelector, "invalid selector");
+        match using.as_str() {
+            "css selector" => {
+                let selector = parse_css(&value).ok_or_else(invalid)?;
+                Ok(candidates
+                    .into_iter()
+                    .filter(|&id| {
+                        selector
+                            .iter()
+                            .any(|complex| self.matches_complex(id, complex, root))
+                    })
+                    .collect())
+            }
+            "xpath" => {
+                let (tag, predicate) = parse_xpath(&value).ok_or_else(invalid)?;
+                Ok(candidates
+                    .into_iter()
+                    .filter(|&id| {
+                        let node = &self.nodes[id];
+                        (tag == "*" || node.tag == tag)
+                            && match predicate {
+                                XPathPredicate::None => true,
+                                XPathPredicate::Attribute(ref name, ref value) => {
+                                    node.attributes.get(name) == Some(value)
+                                }
+                                XPathPredicate::Text(ref value) => self.text(id) == *value,
+                            }
+                    })
+                    .collect())
+            }
+            "link text" | "partial link text" => {
+                let partial = using == "partial link text";
+                Ok(candidates
+                    .into_iter()
+                    .filter(|&id| {
+                        let text = self.text(id);
+                        self.nodes[id].tag == "a"
+                            && if partial {
+                                text.trim().contains(value.as_str())
+                            } else {
+                                text.trim() == value
+                            }
+                    })
+                    .collect())
+            }
+            "tag name" => Ok(candidates
+                .into_iter()
+                .filter(|&id| self.nodes[id].tag.eq_ignore_ascii_case(&value))
+                .collect()),
+            _ => Err(invalid_argument("unknown locator strategy")),
+        }
+    }
+
+    fn matches_compound(&self, id: usize, compound: &Compound) -> bool {
+        let node = &self.nodes[id];
+        if let Some(ref tag) = compound.tag {
+            if *tag != node.tag {
+                return false;
+            }
+        }
+        if let Some(ref wanted) = compound.id {
+            if node.attributes.get("id") != Some(wanted) {
+                return false;
+            }
+        }
+        let classes: Vec<&str> = node
+            .attributes
+            .get("class")
+            .map(|c| c.split_whitespace().collect())
+            .unwrap_or_default();
+        if !compound
+            .classes
+            .iter()
+            .all(|c| classes.contains(&c.as_str()))
+        {
+            return false;
+        }
+        compound
+            .attributes
+            .iter()
+            .all(|(name, value)| match (node.attributes.get(name), value) {
+                (Some(_), None) => true,
+                (Some(actual), Some(wanted)) => actual == wanted,
+                (None, _) => false,
+            })
+    }
+
+    /// Match a complex selector right-to-left, without leaving the subtree below `scope`.
+    fn matches_complex(
+        &self,
+        id: usize,
+        complex: &[(Combinator, Compound)],
+        scope: Option<usize>,
+    ) -> bool {
+        let Some(((combinator, last), rest)) = complex.split_last() else {
+            return true;
+        };
+        if !self.matches_compound(id, last) {
+            return false;
+        }
+        if rest.is_empty() {
+            return true;
+        }
+        let mut parent = self.nodes[id].parent;
+        while let Some(p) = parent {
+            if Some(p) == scope {
+                return false;
+            }
+            if self.matches_complex(p, rest, scope) {
+                return true;
+            }
+            if *combinator == Combinator::Child {
+                return false;
+            }
+            parent = self.nodes[p].parent;
+        }
+        false
+    }
+}
+
+fn screenshot() -> String {
+    base64::engine::general_purpose::STANDARD.encode(b"\x89PNG\r\n\x1a\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// Parse a selector list into complex selectors.
+///
+/// Each compound selector is paired with the combinator that joins it to the compound selector
+/// *before* it, which is what right-to-left matching needs.
+fn parse_css(selector: &str) -> Option<Vec<Vec<(Combinator, Compound)>>> {
+    selector
+        .split(',')
+        .map(|complex| {
+            let mut parts = Vec::new();
+            let mut next = Combinator::Descendant;
+            for token in complex.replace('>', " > ").split_whitespace() {
+                if token == ">" {
+                    next = Combinator::Child;
+                    continue;
+                }
+                parts.push((next, parse_compound(token)?));
+                next = Combinator::Descendant;
+            }
+            if parts.is_empty() {
+                return None;
+            }
+            Some(parts)
+        })
+        .collect()
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn parse_compound(token: &str) -> Option<Compound> {
+    let mut compound = Compound::default();
+    let mut rest = token;
+
+    let tag_end = rest.find(['#', '.', '[']).unwrap_or(rest.len());
+    let tag = &rest[..tag_end];
+    if !tag.is_empty() && tag != "*" {
+        if !is_ident(tag) {
+            return None;
+        }
+        compound.tag = Some(tag.to_ascii_lowercase());
+    }
+    rest = &rest[tag_end..];
+
+    while !rest.is_empty() {
+        let kind = rest.chars().next()?;
+        let body = &rest[kind.len_utf8()..];
+        match kind {
+            '#' | '.' => {
+                let end = body.find(['#', '.', '[']).unwrap_or(body.len());
+                let name = &body[..end];
+                if !is_ident(name) {
+                    return None;
+                }
+                if kind == '#' {
+                    compound.id = Some(name.to_string());
+                } else {
+                    compound.classes.push(name.to_string());
+                }
+                rest = &body[end..];
+            }
+            '[' => {
+                let end = body.find(']')?;
+                let attr = &body[..end];
+                match attr.split_once('=') {
+                    Some((name, value)) => {
+                        let value = value.trim();
+                        let value = value
+                            .strip_prefix('"')
+                            .and_then(|v| v.strip_suffix('"'))
+                            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                            .unwrap_or(value);
+                        compound
+                            .attributes
+                            .push((name.trim().to_string(), Some(value.to_string())));
+                    }
+                    None => compound.attributes.push((attr.trim().to_string(), None)),
+                }
+                rest = &body[end + 1..];
+            }
+            _ => return None,
+        }
+    }
+    Some(compound)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum XPathPredicate {
+    None,
+    Attribute(String, String),
+    Text(String),
+}
+
+/// Parse the handful of XPath shapes that fantoccini itself produces, such as
+/// `//*[@id="foo"]` and `.//option[.='bar']`.
+fn parse_xpath(xpath: &str) -> Option<(String, XPathPredicate)> {
+    let rest = xpath.strip_prefix('.').unwrap_or(xpath);
+    let rest = rest.strip_prefix("//")?;
+    let (tag, predicate) = match rest.split_once('[') {
+        Some((tag, predicate)) => (tag, Some(predicate.strip_suffix(']')?)),
+        None => (rest, None),
+    };
+    if tag.is_empty() || tag.contains(['/', '[', ']']) {
+        return None;
+    }
+    let predicate = match predicate {
+        None => XPathPredicate::None,
+        Some(predicate) => {
+            let (lhs, rhs) = predicate.split_once('=')?;
+            let rhs = rhs.trim();
+            let value = rhs
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| rhs.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))?
+                .to_string();
+            match lhs.trim() {
+                "." | "text()" => XPathPredicate::Text(value),
+                attr => XPathPredicate::Attribute(attr.strip_prefix('@')?.to_string(), value),
+            }
+        }
+    };
+    Some((tag.to_ascii_lowercase(), predicate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_parsing() {
+        let selector = parse_css("div#main > a.link[href], span").unwrap();
+        assert_eq!(selector.len(), 2);
+        assert_eq!(selector[0].len(), 2);
+        assert_eq!(selector[0][1].0, Combinator::Child);
+        assert_eq!(selector[0][0].1.id.as_deref(), Some("main"));
+        assert_eq!(
+            selector[0][1].1.attributes,
+            vec![("href".to_string(), None)]
+        );
+        assert!(parse_css("a:hover").is_none());
+        assert!(parse_css("a[href]é").is_none());
+    }
+
+    #[test]
+    fn xpath_parsing() {
+        assert_eq!(
+            parse_xpath(r#"//*[@id="foo"]"#),
+            Some((
+                "*".to_string(),
+                XPathPredicate::Attribute("id".to_string(), "foo".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_xpath(".//option[.='bar']"),
+            Some((
+                "option".to_string(),
+                XPathPredicate::Text("bar".to_string())
+            ))
+        );
+        assert!(parse_xpath("/html/body").is_none());
+    }
+}
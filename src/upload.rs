@@ -0,0 +1,46 @@
+//! Sending files to a Selenium server, for [`Element::upload_files`].
+//!
+//! [`Element::upload_files`]: crate::elements::Element::upload_files
+use crate::wd::WebDriverCompatibleCommand;
+use base64::Engine;
+use http::Method;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use url::{ParseError, Url};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Selenium's command for sending a file to the machine that the browser runs on.
+#[derive(Debug)]
+pub(crate) struct UploadFile {
+    /// A base64-encoded zip archive with the file.
+    pub(crate) file: String,
+}
+
+impl WebDriverCompatibleCommand for UploadFile {
+    fn endpoint(&self, base_url: &Url, session_id: Option<&str>) -> Result<Url, ParseError> {
+        base_url.join(&format!(
+            "session/{}/se/file",
+            session_id.unwrap_or_default()
+        ))
+    }
+
+    fn method_and_body(&self, _: &Url) -> (Method, Option<String>) {
+        let body = serde_json::json!({ "file": self.file });
+        (Method::POST, Some(body.to_string()))
+    }
+}
+
+/// Package the file at `path` as a base64-encoded zip archive, as [`UploadFile`] expects it.
+pub(crate) fn zip_file(path: &Path) -> io::Result<String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file(name.to_string_lossy(), options)?;
+    zip.write_all(&fs::read(path)?)?;
+    let zip = zip.finish()?.into_inner();
+    Ok(base64::engine::general_purpose::STANDARD.encode(zip))
+}
@@ -44,6 +44,7 @@
 //! When a wait operation times out, it will return a [`CmdError::WaitTimeout`]. When a wait
 //! condition check returns an error, the wait operation will be aborted, and the error returned.
 
+use crate::client::SearchRoot;
 use crate::elements::Element;
 use crate::error::{CmdError, ErrorStatus};
 use crate::wd::Locator;
@@ -135,7 +136,7 @@ impl<'c> Wait<'c> {
     /// Wait until a particular element can be found.
     pub async fn for_element(self, search: Locator<'_>) -> Result<Element, CmdError> {
         wait_on!(self, {
-            match self.client.by(search.into_parameters()).await {
+            match self.client.locate(SearchRoot::Document, search).await {
                 Ok(element) => Ok(Some(element)),
                 Err(CmdError::Standard(w)) if w.error == ErrorStatus::NoSuchElement => Ok(None),
                 Err(err) => Err(err),
@@ -1,5 +1,6 @@
 //! WebDriver types and declarations.
 use crate::error;
+use crate::options::{PageLoadStrategy, ProxyConfig};
 #[cfg(doc)]
 use crate::Client;
 use http::Method;
@@ -44,6 +45,26 @@ pub trait WebDriverCompatibleCommand: Debug {
     fn is_legacy(&self) -> bool {
         false
     }
+
+    /// Return true if this command only reads state, and so does not need to be ordered with
+    /// respect to other commands that only read state.
+    ///
+    /// With [concurrent commands](crate::ClientBuilder::concurrent_commands) enabled, read-only
+    /// commands may be in flight at the same time. Commands that are not read-only are only
+    /// issued once all earlier commands have completed, and hold back all later commands until
+    /// they complete themselves.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Return true if issuing this command twice has the same effect as issuing it once.
+    ///
+    /// A [`RetryPolicy`] only retries idempotent commands, unless it is configured to
+    /// [retry all commands](RetryPolicy::retry_non_idempotent). Defaults to
+    /// [`is_read_only`](WebDriverCompatibleCommand::is_read_only).
+    fn is_idempotent(&self) -> bool {
+        self.is_read_only()
+    }
 }
 
 /// Blanket implementation for &T, for better ergonomics.
@@ -66,6 +87,14 @@ where
     fn is_legacy(&self) -> bool {
         T::is_legacy(self)
     }
+
+    fn is_read_only(&self) -> bool {
+        T::is_read_only(self)
+    }
+
+    fn is_idempotent(&self) -> bool {
+        T::is_idempotent(self)
+    }
 }
 
 /// Blanket implementation for Box<T>, for better ergonomics.
@@ -88,6 +117,14 @@ where
     fn is_legacy(&self) -> bool {
         T::is_legacy(self)
     }
+
+    fn is_read_only(&self) -> bool {
+        T::is_read_only(self)
+    }
+
+    fn is_idempotent(&self) -> bool {
+        T::is_idempotent(self)
+    }
 }
 
 /// A [handle][1] to a browser window.
@@ -187,10 +224,19 @@ pub type Capabilities = serde_json::Map<String, serde_json::Value>;
 
 /// An element locator.
 ///
-/// See [the specification][1] for more details.
+/// `Css`, `LinkText`, `PartialLinkText`, `TagName` and `XPath` are the [locator strategies][1]
+/// of the WebDriver standard. The others are conveniences: `Id`, `Name` and `ClassName` are
+/// rewritten into CSS selectors or XPath expressions, while `Text` and `Role` are looked up by a
+/// script that runs in the page. Script-based lookups are not retried until the [implicit wait
+/// timeout](TimeoutConfiguration::implicit) expires; use [`Wait::for_element`] to wait for them.
+///
+/// More locators may be added without a major version bump, so matching on a `Locator` needs a
+/// wildcard arm.
 ///
 /// [1]: https://www.w3.org/TR/webdriver1/#locator-strategies
+/// [`Wait::for_element`]: crate::wait::Wait::for_element
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+#[non_exhaustive]
 pub enum Locator<'a> {
     /// Find an element matching the given [CSS selector][1].
     ///
@@ -207,6 +253,53 @@ pub enum Locator<'a> {
     /// The text matching is exact.
     LinkText(&'a str),
 
+    /// Find a link element whose link text contains the given text.
+    PartialLinkText(&'a str),
+
+    /// Find an element with the given tag name, such as `"button"`.
+    TagName(&'a str),
+
+    /// Find a form control, or other element, with the given `name` attribute.
+    Name(&'a str),
+
+    /// Find an element with the given class among its [`class`][1] attribute.
+    ///
+    /// [1]: https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/class
+    ClassName(&'a str),
+
+    /// Find the innermost element whose text content matches `text`.
+    ///
+    /// Runs of whitespace are collapsed and leading and trailing whitespace is ignored, both in
+    /// `text` and in the page. If `exact` is `false`, elements whose text merely contains `text`
+    /// match as well. Of an element and its descendants that all match, only the innermost one
+    /// is found, so `<p>Hello <b>world</b></p>` is found for `"Hello world"`, and `<b>` is
+    /// found for `"world"`.
+    Text {
+        /// The text to look for.
+        text: &'a str,
+        /// Whether the text must match in full, rather than be contained in the element's text.
+        exact: bool,
+    },
+
+    /// Find an element with the given [ARIA role][1], and optionally the given accessible name.
+    ///
+    /// Candidates are elements that have `role` as their `role` attribute, or as the implicit
+    /// role of their HTML element. Where the driver supports [Get Computed Role][2] and [Get
+    /// Computed Label][3], those decide which candidates match. Otherwise, the accessible name is
+    /// approximated from `aria-labelledby`, `aria-label`, associated `<label>`s, `alt`, `value`,
+    /// the element's text and `title`, in that order. Names are compared after collapsing
+    /// whitespace.
+    ///
+    /// [1]: https://www.w3.org/TR/wai-aria/#role_definitions
+    /// [2]: https://www.w3.org/TR/webdriver2/#get-computed-role
+    /// [3]: https://www.w3.org/TR/webdriver2/#get-computed-label
+    Role {
+        /// The role to look for, such as `"button"` or `"heading"`.
+        role: &'a str,
+        /// The accessible name the element must have, if any.
+        name: Option<&'a str>,
+    },
+
     /// Find an element using the given [XPath expression][1].
     ///
     /// You can address pretty much any element this way, if you're willing to
@@ -216,32 +309,310 @@ pub enum Locator<'a> {
     XPath(&'a str),
 }
 
+/// How a [`Locator`] is resolved.
+#[derive(Debug)]
+pub(crate) enum Lookup {
+    /// With one of the locator strategies of the WebDriver standard.
+    Native(webdriver::command::LocatorParameters),
+    /// By running `script` in the page, with the root to search in prepended to `args`.
+    Script {
+        script: &'static str,
+        args: Vec<serde_json::Value>,
+    },
+}
+
+/// Functions shared by the lookup scripts, which are given the root to search in as their first
+/// argument.
+macro_rules! lookup_script {
+    ($body:expr) => {
+        concat!(
+            "const scope = arguments[0] || document;\n",
+            "const normalize = (s) => (s || '').replace(/\\s+/g, ' ').trim();\n",
+            $body
+        )
+    };
+}
+
+/// Find the innermost elements whose text is, or contains, `arguments[1]`.
+const TEXT_LOOKUP: &str = lookup_script!(
+    r#"
+const [, wanted, exact] = arguments;
+const text = normalize(wanted);
+const skipped = ['SCRIPT', 'STYLE', 'TEMPLATE', 'NOSCRIPT'];
+const matches = (el) => {
+    if (skipped.includes(el.tagName.toUpperCase())) return false;
+    const content = normalize(el.textContent);
+    return exact ? content === text : content.includes(text);
+};
+return Array.from(scope.querySelectorAll('*'))
+    .filter((el) => matches(el) && !Array.from(el.children).some(matches));
+"#
+);
+
+/// JavaScript functions that approximate the ARIA `role(el)` and accessible `name(el)` of an
+/// element. They expect a `normalize(s)` function that collapses whitespace.
+macro_rules! aria_functions {
+    () => {
+        r#"
+const byTag = {
+    article: 'article', aside: 'complementary', button: 'button', datalist: 'listbox',
+    details: 'group', dialog: 'dialog', fieldset: 'group', figure: 'figure', h1: 'heading',
+    h2: 'heading', h3: 'heading', h4: 'heading', h5: 'heading', h6: 'heading', hr: 'separator',
+    li: 'listitem', main: 'main', menu: 'list', meter: 'meter', nav: 'navigation', ol: 'list',
+    optgroup: 'group', option: 'option', output: 'status', p: 'paragraph',
+    progress: 'progressbar', search: 'search', table: 'table', tbody: 'rowgroup', td: 'cell',
+    textarea: 'textbox', tfoot: 'rowgroup', th: 'columnheader', thead: 'rowgroup', tr: 'row',
+    ul: 'list',
+};
+const byInputType = {
+    button: 'button', checkbox: 'checkbox', email: 'textbox', image: 'button', number: 'spinbutton',
+    radio: 'radio', range: 'slider', reset: 'button', search: 'searchbox', submit: 'button',
+    tel: 'textbox', text: 'textbox', url: 'textbox',
+};
+const sectioning = 'article, aside, main, nav, section';
+const labelled = (el) => el.hasAttribute('aria-label') || el.hasAttribute('aria-labelledby');
+const implicitRole = (el) => {
+    const tag = el.localName;
+    switch (tag) {
+        case 'a':
+        case 'area':
+            return el.hasAttribute('href') ? 'link' : null;
+        case 'img':
+            return el.getAttribute('alt') === '' ? 'presentation' : 'img';
+        case 'input': {
+            const type = (el.getAttribute('type') || 'text').toLowerCase();
+            if (el.hasAttribute('list') && ['email', 'search', 'tel', 'text', 'url'].includes(type)) {
+                return 'combobox';
+            }
+            return byInputType[type] || null;
+        }
+        case 'select':
+            return el.multiple || el.size > 1 ? 'listbox' : 'combobox';
+        case 'header':
+            return el.parentElement && el.parentElement.closest(sectioning) ? null : 'banner';
+        case 'footer':
+            return el.parentElement && el.parentElement.closest(sectioning) ? null : 'contentinfo';
+        case 'section':
+            return labelled(el) ? 'region' : null;
+        case 'form':
+            return labelled(el) || el.hasAttribute('name') ? 'form' : null;
+        default:
+            return byTag[tag] || null;
+    }
+};
+const role = (el) => {
+    const explicit = (el.getAttribute('role') || '').trim().split(/\s+/)[0];
+    return (explicit || implicitRole(el) || '').toLowerCase();
+};
+const name = (el) => {
+    const root = el.getRootNode();
+    const ids = (el.getAttribute('aria-labelledby') || '').split(/\s+/).filter((id) => id);
+    const labelledBy = normalize(
+        ids.map((id) => root.getElementById(id)).filter((e) => e).map((e) => e.textContent).join(' ')
+    );
+    if (labelledBy) return labelledBy;
+    const label = normalize(el.getAttribute('aria-label'));
+    if (label) return label;
+    if (el.labels && el.labels.length) {
+        return normalize(Array.from(el.labels).map((l) => l.textContent).join(' '));
+    }
+    if (el.hasAttribute('alt')) return normalize(el.getAttribute('alt'));
+    if (el.localName === 'input' && ['button', 'reset', 'submit'].includes(el.type)) {
+        return normalize(el.value);
+    }
+    return normalize(el.textContent) || normalize(el.getAttribute('title'));
+};
+"#
+    };
+}
+
+/// The functions of [`aria_functions!`], for scripts that are assembled at runtime.
+pub(crate) const ARIA_FUNCTIONS: &str = aria_functions!();
+
+/// Find the elements whose explicit or implicit ARIA role is `arguments[1]`, along with an
+/// approximation of their accessible names.
+const ROLE_LOOKUP: &str = lookup_script!(concat!(
+    aria_functions!(),
+    r#"
+const wanted = normalize(arguments[1]).toLowerCase();
+return Array.from(scope.querySelectorAll('*'))
+    .filter((el) => role(el) === wanted)
+    .map((el) => [el, name(el)]);
+"#
+));
+
+/// Quote `s` as a CSS string.
+fn css_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\0' => quoted.push('\u{fffd}'),
+            c if c.is_control() => quoted.push_str(&format!("\\{:x} ", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escape `s` for use as a CSS identifier, like [`CSS.escape()`][1].
+///
+/// [1]: https://drafts.csswg.org/cssom/#the-css.escape()-method
+fn css_ident(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    let leading_dash = s.starts_with('-');
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\0' => escaped.push('\u{fffd}'),
+            c if c.is_control() => escaped.push_str(&format!("\\{:x} ", c as u32)),
+            '0'..='9' if i == 0 || (i == 1 && leading_dash) => {
+                escaped.push_str(&format!("\\{:x} ", c as u32))
+            }
+            '-' if i == 0 && s.len() == 1 => escaped.push_str("\\-"),
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii() => {
+                escaped.push(c)
+            }
+            c => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+        }
+    }
+    escaped
+}
+
 impl<'a> Locator<'a> {
-    pub(crate) fn into_parameters(self) -> webdriver::command::LocatorParameters {
+    pub(crate) fn into_lookup(self) -> Lookup {
         use webdriver::command::LocatorParameters;
         use webdriver::common::LocatorStrategy;
 
+        let (using, value) = match self {
+            Locator::Css(s) => (LocatorStrategy::CSSSelector, s.to_string()),
+            Locator::Id(s) => (LocatorStrategy::XPath, format!("//*[@id=\"{}\"]", s)),
+            Locator::XPath(s) => (LocatorStrategy::XPath, s.to_string()),
+            Locator::LinkText(s) => (LocatorStrategy::LinkText, s.to_string()),
+            Locator::PartialLinkText(s) => (LocatorStrategy::PartialLinkText, s.to_string()),
+            Locator::TagName(s) => (LocatorStrategy::TagName, s.to_string()),
+            Locator::Name(s) => (
+                LocatorStrategy::CSSSelector,
+                format!("[name={}]", css_string(s)),
+            ),
+            Locator::ClassName(s) => (LocatorStrategy::CSSSelector, format!(".{}", css_ident(s))),
+            Locator::Text { text, exact } => {
+                return Lookup::Script {
+                    script: TEXT_LOOKUP,
+                    args: vec![text.into(), exact.into()],
+                }
+            }
+            Locator::Role { role, .. } => {
+                return Lookup::Script {
+                    script: ROLE_LOOKUP,
+                    args: vec![role.into()],
+                }
+            }
+        };
+        Lookup::Native(LocatorParameters { using, value })
+    }
+}
+
+/// An owned [`Locator`].
+///
+/// Unlike a `Locator`, a `LocatorBuf` does not borrow its arguments, so it can be stored in
+/// structs or built at runtime. Use [`LocatorBuf::as_locator`] to search with it. Like
+/// `Locator`, it may gain variants without a major version bump.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+#[non_exhaustive]
+pub enum LocatorBuf {
+    /// See [`Locator::Css`].
+    Css(String),
+    /// See [`Locator::Id`].
+    Id(String),
+    /// See [`Locator::LinkText`].
+    LinkText(String),
+    /// See [`Locator::PartialLinkText`].
+    PartialLinkText(String),
+    /// See [`Locator::TagName`].
+    TagName(String),
+    /// See [`Locator::Name`].
+    Name(String),
+    /// See [`Locator::ClassName`].
+    ClassName(String),
+    /// See [`Locator::Text`].
+    Text {
+        /// The text to look for.
+        text: String,
+        /// Whether the text must match in full, rather than be contained in the element's text.
+        exact: bool,
+    },
+    /// See [`Locator::Role`].
+    Role {
+        /// The role to look for.
+        role: String,
+        /// The accessible name the element must have, if any.
+        name: Option<String>,
+    },
+    /// See [`Locator::XPath`].
+    XPath(String),
+}
+
+impl LocatorBuf {
+    /// Borrow this as a [`Locator`].
+    pub fn as_locator(&self) -> Locator<'_> {
         match self {
-            Locator::Css(s) => LocatorParameters {
-                using: LocatorStrategy::CSSSelector,
-                value: s.to_string(),
+            LocatorBuf::Css(s) => Locator::Css(s),
+            LocatorBuf::Id(s) => Locator::Id(s),
+            LocatorBuf::LinkText(s) => Locator::LinkText(s),
+            LocatorBuf::PartialLinkText(s) => Locator::PartialLinkText(s),
+            LocatorBuf::TagName(s) => Locator::TagName(s),
+            LocatorBuf::Name(s) => Locator::Name(s),
+            LocatorBuf::ClassName(s) => Locator::ClassName(s),
+            LocatorBuf::Text { text, exact } => Locator::Text {
+                text,
+                exact: *exact,
             },
-            Locator::Id(s) => LocatorParameters {
-                using: LocatorStrategy::XPath,
-                value: format!("//*[@id=\"{}\"]", s),
+            LocatorBuf::Role { role, name } => Locator::Role {
+                role,
+                name: name.as_deref(),
             },
-            Locator::XPath(s) => LocatorParameters {
-                using: LocatorStrategy::XPath,
-                value: s.to_string(),
+            LocatorBuf::XPath(s) => Locator::XPath(s),
+        }
+    }
+}
+
+impl From<Locator<'_>> for LocatorBuf {
+    fn from(locator: Locator<'_>) -> Self {
+        match locator {
+            Locator::Css(s) => LocatorBuf::Css(s.to_string()),
+            Locator::Id(s) => LocatorBuf::Id(s.to_string()),
+            Locator::LinkText(s) => LocatorBuf::LinkText(s.to_string()),
+            Locator::PartialLinkText(s) => LocatorBuf::PartialLinkText(s.to_string()),
+            Locator::TagName(s) => LocatorBuf::TagName(s.to_string()),
+            Locator::Name(s) => LocatorBuf::Name(s.to_string()),
+            Locator::ClassName(s) => LocatorBuf::ClassName(s.to_string()),
+            Locator::Text { text, exact } => LocatorBuf::Text {
+                text: text.to_string(),
+                exact,
             },
-            Locator::LinkText(s) => LocatorParameters {
-                using: LocatorStrategy::LinkText,
-                value: s.to_string(),
+            Locator::Role { role, name } => LocatorBuf::Role {
+                role: role.to_string(),
+                name: name.map(str::to_string),
             },
+            Locator::XPath(s) => LocatorBuf::XPath(s.to_string()),
         }
     }
 }
 
+impl<'a> From<&'a LocatorBuf> for Locator<'a> {
+    fn from(locator: &'a LocatorBuf) -> Self {
+        locator.as_locator()
+    }
+}
+
 /// The WebDriver status as returned by [`Client::status()`].
 ///
 /// See [8.3 Status](https://www.w3.org/TR/webdriver1/#status) of the WebDriver standard.
@@ -284,9 +655,9 @@ impl TimeoutConfiguration {
     ///
     /// The various settings are as follows:
     /// - script     Determines when to interrupt a script that is being evaluated.
-    ///              Default is 60 seconds.
+    ///   Default is 60 seconds.
     /// - page_load  Provides the timeout limit used to interrupt navigation of the browsing
-    ///              context. Default is 60 seconds.
+    ///   context. Default is 60 seconds.
     /// - implicit   Gives the timeout of when to abort locating an element. Default is 0 seconds.
     ///
     /// NOTE: It is recommended to leave the `implicit` timeout at 0 seconds, because that makes
@@ -345,6 +716,96 @@ impl TimeoutConfiguration {
     }
 }
 
+/// A policy for retrying commands that fail because of a transport problem.
+///
+/// When a command fails because the connection to the WebDriver server was lost or could not be
+/// established (that is, with [`CmdError::Lost`], [`CmdError::Failed`], or
+/// [`CmdError::FailedC`]), it is issued again after a delay, up to a maximum number of attempts.
+/// The delay starts out at the initial backoff, and doubles after every attempt, up to the maximum
+/// backoff. Errors reported by the WebDriver server itself are never retried.
+///
+/// Only [idempotent](WebDriverCompatibleCommand::is_idempotent) commands are retried by default,
+/// since a command like [`Client::goto`] or [`Element::click`](crate::elements::Element::click)
+/// may have taken effect even though its response was lost.
+///
+/// Used by [`ClientBuilder::retry_policy`](crate::ClientBuilder::retry_policy).
+///
+/// [`CmdError::Lost`]: error::CmdError::Lost
+/// [`CmdError::Failed`]: error::CmdError::Failed
+/// [`CmdError::FailedC`]: error::CmdError::FailedC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(3)
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy that issues a command at most `max_attempts` times in total.
+    ///
+    /// The backoff starts at 100 milliseconds, and is capped at 5 seconds.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            non_idempotent: false,
+        }
+    }
+
+    /// Set the delay before the first retry, and the maximum delay between any two attempts.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Also retry commands that are not idempotent.
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.non_idempotent = retry;
+        self
+    }
+
+    /// Get the maximum number of times a command is issued.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The delay before issuing a command for the `attempt`-th time (counting from 1) after it
+    /// failed with `error`, or `None` if it should not be retried.
+    pub(crate) fn retry_after(
+        &self,
+        error: &error::CmdError,
+        attempt: u32,
+        idempotent: bool,
+    ) -> Option<Duration> {
+        use error::CmdError;
+
+        if attempt > self.max_attempts || !(idempotent || self.non_idempotent) {
+            return None;
+        }
+        if !matches!(
+            error,
+            CmdError::Lost(..) | CmdError::Failed(..) | CmdError::FailedC(..)
+        ) {
+            return None;
+        }
+        let factor = 1u32 << attempt.saturating_sub(2).min(16);
+        Some(
+            self.initial_backoff
+                .saturating_mul(factor)
+                .min(self.max_backoff),
+        )
+    }
+}
+
 /// The response obtained when opening the WebDriver session.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -352,6 +813,8 @@ pub struct NewSessionResponse {
     #[serde(rename = "sessionId")]
     session_id: String,
     capabilities: Option<Capabilities>,
+    #[serde(skip)]
+    matched_alternative: Option<usize>,
 }
 
 impl NewSessionResponse {
@@ -365,14 +828,281 @@ impl NewSessionResponse {
         self.capabilities.as_ref()
     }
 
-    pub(crate) fn from_wd(nsr: webdriver::response::NewSessionResponse) -> Self {
+    /// Get the index of the [`firstMatch`](crate::ClientBuilder::first_match) alternative that
+    /// the remote end chose.
+    ///
+    /// The remote end does not say which alternative it chose, so this is worked out by comparing
+    /// the alternatives against the standard capabilities it returned. Returns `None` if no
+    /// alternatives were given, or unless exactly one of them is consistent with the remote end
+    /// capabilities. In particular, alternatives that only differ in vendor-prefixed
+    /// capabilities, such as two sets of `goog:chromeOptions`, cannot be told apart.
+    pub fn matched_alternative(&self) -> Option<usize> {
+        self.matched_alternative
+    }
+
+    /// Get a typed view of the remote end capabilities.
+    pub fn session_capabilities(&self) -> Option<SessionCapabilities> {
+        self.capabilities.as_ref().map(SessionCapabilities::from)
+    }
+
+    pub(crate) fn from_wd(
+        nsr: webdriver::response::NewSessionResponse,
+        first_match: &[Capabilities],
+    ) -> Self {
+        let capabilities = nsr.capabilities.as_object().cloned();
+        let matched_alternative = capabilities.as_ref().and_then(|caps| {
+            let mut consistent = first_match
+                .iter()
+                .enumerate()
+                .filter(|(_, alternative)| is_consistent(alternative, caps))
+                .map(|(i, _)| i);
+            match (consistent.next(), consistent.next()) {
+                (Some(i), None) => Some(i),
+                // ambiguous, so don't guess
+                _ => None,
+            }
+        });
         NewSessionResponse {
             session_id: nsr.session_id,
-            capabilities: nsr.capabilities.as_object().cloned(),
+            capabilities,
+            matched_alternative,
+        }
+    }
+}
+
+/// The capabilities of a session, as returned by the remote end when the session was opened.
+///
+/// This is a typed view of [`Client::capabilities`]. Each standard capability is `None` if the
+/// remote end did not return it, or returned it in a form that could not be parsed. Vendor-prefixed
+/// capabilities, such as `moz:processID` or `goog:chromeOptions`, are kept as JSON in
+/// [`extensions`](Self::extensions).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct SessionCapabilities {
+    browser_name: Option<String>,
+    browser_version: Option<String>,
+    platform_name: Option<String>,
+    accept_insecure_certs: Option<bool>,
+    page_load_strategy: Option<PageLoadStrategy>,
+    proxy: Option<ProxyConfig>,
+    set_window_rect: Option<bool>,
+    timeouts: Option<TimeoutConfiguration>,
+    strict_file_interactability: Option<bool>,
+    unhandled_prompt_behavior: Option<String>,
+    web_socket_url: Option<Url>,
+    extensions: Capabilities,
+}
+
+impl SessionCapabilities {
+    /// The name of the browser, such as `"firefox"` or `"chrome"`.
+    pub fn browser_name(&self) -> Option<&str> {
+        self.browser_name.as_deref()
+    }
+
+    /// The version of the browser.
+    pub fn browser_version(&self) -> Option<&str> {
+        self.browser_version.as_deref()
+    }
+
+    /// The name of the operating system the browser runs on, such as `"linux"`.
+    pub fn platform_name(&self) -> Option<&str> {
+        self.platform_name.as_deref()
+    }
+
+    /// Whether untrusted and self-signed TLS certificates are trusted.
+    pub fn accept_insecure_certs(&self) -> Option<bool> {
+        self.accept_insecure_certs
+    }
+
+    /// When navigation commands return.
+    pub fn page_load_strategy(&self) -> Option<PageLoadStrategy> {
+        self.page_load_strategy
+    }
+
+    /// The session's proxy configuration.
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    /// Whether the remote end supports resizing and positioning windows.
+    pub fn set_window_rect(&self) -> Option<bool> {
+        self.set_window_rect
+    }
+
+    /// The timeouts the session started out with.
+    pub fn timeouts(&self) -> Option<&TimeoutConfiguration> {
+        self.timeouts.as_ref()
+    }
+
+    /// Whether interacting with file inputs requires them to be interactable.
+    pub fn strict_file_interactability(&self) -> Option<bool> {
+        self.strict_file_interactability
+    }
+
+    /// What happens to user prompts, such as alerts, that a command runs into.
+    pub fn unhandled_prompt_behavior(&self) -> Option<&str> {
+        self.unhandled_prompt_behavior.as_deref()
+    }
+
+    /// The [WebDriver BiDi](https://w3c.github.io/webdriver-bidi/) endpoint of the session.
+    ///
+    /// This is only returned if it was requested by setting `webSocketUrl` to `true`.
+    pub fn web_socket_url(&self) -> Option<&Url> {
+        self.web_socket_url.as_ref()
+    }
+
+    /// The vendor-prefixed capabilities, such as `moz:processID`.
+    pub fn extensions(&self) -> &Capabilities {
+        &self.extensions
+    }
+
+    /// Get the vendor-prefixed capability `name`.
+    pub fn extension(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extensions.get(name)
+    }
+}
+
+impl From<&Capabilities> for SessionCapabilities {
+    fn from(caps: &Capabilities) -> Self {
+        fn get<T: serde::de::DeserializeOwned>(caps: &Capabilities, key: &str) -> Option<T> {
+            serde_json::from_value(caps.get(key)?.clone()).ok()
+        }
+
+        SessionCapabilities {
+            browser_name: get(caps, "browserName"),
+            browser_version: get(caps, "browserVersion"),
+            platform_name: get(caps, "platformName"),
+            accept_insecure_certs: get(caps, "acceptInsecureCerts"),
+            page_load_strategy: get(caps, "pageLoadStrategy"),
+            proxy: get(caps, "proxy"),
+            set_window_rect: get(caps, "setWindowRect"),
+            timeouts: get(caps, "timeouts"),
+            strict_file_interactability: get(caps, "strictFileInteractability"),
+            unhandled_prompt_behavior: get(caps, "unhandledPromptBehavior"),
+            web_socket_url: get::<String>(caps, "webSocketUrl").and_then(|u| u.parse().ok()),
+            extensions: caps
+                .iter()
+                .filter(|(key, _)| key.contains(':'))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Whether the capabilities the remote end returned could have come from requesting `alternative`.
+///
+/// The remote end does not say which alternative it chose, and it only echoes back standard
+/// capabilities, so this compares those of them that it returned. `browserVersion` is skipped,
+/// since the remote end is free to match a requested version against a more precise one.
+fn is_consistent(alternative: &Capabilities, returned: &Capabilities) -> bool {
+    alternative.iter().all(|(key, requested)| {
+        if key.contains(':') || key == "browserVersion" {
+            return true;
+        }
+        match (key.as_str(), returned.get(key)) {
+            (_, None) => true,
+            ("browserName" | "platformName", Some(serde_json::Value::String(actual))) => requested
+                .as_str()
+                .map_or(false, |requested| same_name(requested, actual)),
+            (_, Some(actual)) => requested == actual,
+        }
+    })
+}
+
+/// Compare browser or platform names the way remote ends do, which is case-insensitively, and
+/// with Edge's drivers reporting `msedge` for a requested `MicrosoftEdge`.
+fn same_name(requested: &str, actual: &str) -> bool {
+    let normalize = |name: &str| match name.to_ascii_lowercase().as_str() {
+        "msedge" => "microsoftedge".to_string(),
+        name => name.to_string(),
+    };
+    normalize(requested) == normalize(actual)
+}
+
+/// Everything needed to take over a WebDriver session from another [`Client`].
+///
+/// A descriptor is produced by [`Client::detach`], which leaves the session running, and can be
+/// serialized, handed to another process, and turned back into a `Client` with
+/// [`ClientBuilder::attach`](crate::ClientBuilder::attach).
+///
+/// Note that if the WebDriver URL contains credentials, so does the descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDescriptor {
+    webdriver: String,
+    session_id: String,
+    capabilities: Option<Capabilities>,
+    user_agent: Option<String>,
+    timeouts: Option<TimeoutConfiguration>,
+}
+
+impl SessionDescriptor {
+    /// Describe the session with the given id on the WebDriver server at `webdriver`.
+    pub fn new(webdriver: impl Into<String>, session_id: impl Into<String>) -> Self {
+        SessionDescriptor {
+            webdriver: webdriver.into(),
+            session_id: session_id.into(),
+            capabilities: None,
+            user_agent: None,
+            timeouts: None,
+        }
+    }
+
+    /// Get the URL of the WebDriver server.
+    pub fn webdriver(&self) -> &str {
+        &self.webdriver
+    }
+
+    /// Get the session id.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Get the capabilities negotiated when the session was created, if known.
+    pub fn capabilities(&self) -> Option<&Capabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Get the User Agent string the client was using, if any.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Get the timeouts of the session when it was detached, if known.
+    pub fn timeouts(&self) -> Option<&TimeoutConfiguration> {
+        self.timeouts.as_ref()
+    }
+
+    pub(crate) fn from_parts(
+        webdriver: String,
+        session_id: String,
+        capabilities: Option<Capabilities>,
+        user_agent: Option<String>,
+        timeouts: TimeoutConfiguration,
+    ) -> Self {
+        SessionDescriptor {
+            webdriver,
+            session_id,
+            capabilities,
+            user_agent,
+            timeouts: Some(timeouts),
+        }
+    }
+
+    pub(crate) fn new_session_response(&self) -> NewSessionResponse {
+        NewSessionResponse {
+            session_id: self.session_id.clone(),
+            capabilities: self.capabilities.clone(),
+            matched_alternative: None,
         }
     }
 }
 
+pub use crate::session::{
+    CommandFuture, CommandRequest, Middleware, Next, NextCommand, Transport, TransportFuture,
+    WireRequest, WireResponse,
+};
+
 pub use crate::print::{
     PrintConfiguration, PrintConfigurationBuilder, PrintMargins, PrintOrientation, PrintPageRange,
     PrintSize,
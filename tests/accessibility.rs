@@ -0,0 +1,149 @@
+//! Accessibility tests
+
+use fantoccini::accessibility::AccessibilityNode;
+use fantoccini::testing::{MockElement, MockResponse, MockWebDriver};
+use fantoccini::Locator;
+use http::Method;
+use serde_json::json;
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("form").id("login").child(
+        MockElement::new("div")
+            .child(MockElement::new("input").attr("name", "user"))
+            .child(MockElement::new("button").text("Log in")),
+    )]
+}
+
+#[tokio::test]
+async fn computed_role_and_label() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    server.respond(
+        Method::GET,
+        "element/*/computedrole",
+        MockResponse::ok(json!("button")),
+    );
+    server.respond(
+        Method::GET,
+        "element/*/computedlabel",
+        MockResponse::ok(json!("Log in")),
+    );
+    let c = common::connect_mock(&server).await;
+
+    let button = c.find(Locator::TagName("button")).await.unwrap();
+    assert_eq!(button.computed_role().await.unwrap(), "button");
+    assert_eq!(button.computed_label().await.unwrap(), "Log in");
+    let paths: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|cmd| cmd.method == Method::GET)
+        .map(|cmd| cmd.path.rsplit('/').next().unwrap().to_string())
+        .collect();
+    assert_eq!(paths, ["computedrole", "computedlabel"]);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn snapshot() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let form = c.find(Locator::Id("login")).await.unwrap();
+    let div = form.find(Locator::TagName("div")).await.unwrap();
+    let input = form.find(Locator::Name("user")).await.unwrap();
+    let button = form.find(Locator::TagName("button")).await.unwrap();
+    server.on_script(
+        "stateOf",
+        json!([
+            form,
+            "form",
+            "",
+            [],
+            [[
+                div,
+                "",
+                "",
+                [],
+                [
+                    [input, "textbox", "", ["required", "focused"], []],
+                    [button, "button", " Log\n in ", [], []],
+                ]
+            ],]
+        ]),
+    );
+
+    // the mock has no computed roles or labels, so the script's approximation is used, and the
+    // <div> without a role is left out
+    let snapshot = form.accessibility_snapshot().await.unwrap();
+    let expected = AccessibilityNode {
+        role: "form".to_string(),
+        name: String::new(),
+        states: vec![],
+        children: vec![
+            AccessibilityNode {
+                role: "textbox".to_string(),
+                name: String::new(),
+                states: vec!["required".to_string(), "focused".to_string()],
+                children: vec![],
+            },
+            AccessibilityNode {
+                role: "button".to_string(),
+                name: "Log in".to_string(),
+                states: vec![],
+                children: vec![],
+            },
+        ],
+    };
+    assert_eq!(snapshot, expected);
+    assert_eq!(
+        serde_json::to_value(&snapshot).unwrap(),
+        json!({
+            "role": "form",
+            "name": "",
+            "children": [
+                { "role": "textbox", "name": "", "states": ["required", "focused"] },
+                { "role": "button", "name": "Log in" },
+            ],
+        })
+    );
+    let roundtrip: AccessibilityNode =
+        serde_json::from_value(serde_json::to_value(&snapshot).unwrap()).unwrap();
+    assert_eq!(roundtrip, snapshot);
+
+    // where the driver computes roles and labels, those are used
+    let path = |e: &fantoccini::elements::Element, what: &str| {
+        format!("element/{}/{}", e.element_id(), what)
+    };
+    server.respond(
+        Method::GET,
+        "element/*/computedrole",
+        MockResponse::ok(json!("generic")),
+    );
+    server.respond(
+        Method::GET,
+        &path(&input, "computedrole"),
+        MockResponse::ok(json!("searchbox")),
+    );
+    server.respond(
+        Method::GET,
+        "element/*/computedlabel",
+        MockResponse::ok(json!("Username")),
+    );
+    let snapshot = form.accessibility_snapshot().await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&snapshot).unwrap(),
+        json!({
+            "role": "generic",
+            "name": "",
+            "children": [
+                { "role": "searchbox", "name": "Username", "states": ["required", "focused"] },
+            ],
+        })
+    );
+
+    c.close().await.unwrap();
+}
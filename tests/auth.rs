@@ -0,0 +1,74 @@
+//! Authentication tests
+
+use fantoccini::testing::MockWebDriver;
+use http::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use http::Method;
+
+mod common;
+
+/// Checks that every request the server received, including the final `DELETE`, has the header.
+fn assert_all_have(server: &MockWebDriver, name: &HeaderName, value: &str) {
+    let received = server.received();
+    assert_eq!(received.last().unwrap().method, Method::DELETE);
+    for cmd in received {
+        assert_eq!(
+            cmd.headers.get(name).and_then(|v| v.to_str().ok()),
+            Some(value),
+            "{} {}",
+            cmd.method,
+            cmd.path
+        );
+    }
+}
+
+#[tokio::test]
+async fn basic_auth() {
+    let server = MockWebDriver::start().await.unwrap();
+    let mut builder = common::builder();
+    let c = builder
+        .basic_auth("alice", "s3cret")
+        .connect(&server.url())
+        .await
+        .unwrap();
+    c.title().await.unwrap();
+    c.close().await.unwrap();
+
+    // base64("alice:s3cret")
+    assert_all_have(&server, &AUTHORIZATION, "Basic YWxpY2U6czNjcmV0");
+}
+
+#[tokio::test]
+async fn credentials_from_url() {
+    let server = MockWebDriver::start().await.unwrap();
+    let url = server.url().replace("http://", "http://bob:hunter2@");
+    let c = common::builder().connect(&url).await.unwrap();
+    c.title().await.unwrap();
+    c.close().await.unwrap();
+
+    // base64("bob:hunter2")
+    assert_all_have(&server, &AUTHORIZATION, "Basic Ym9iOmh1bnRlcjI=");
+}
+
+#[tokio::test]
+async fn bearer_auth_and_default_headers() {
+    let server = MockWebDriver::start().await.unwrap();
+    let mut builder = common::builder();
+    let project = HeaderName::from_static("x-project");
+    let c = builder
+        .bearer_auth("t0ken")
+        .unwrap()
+        .default_header(project.clone(), HeaderValue::from_static("fantoccini"))
+        .connect(&server.url())
+        .await
+        .unwrap();
+    c.title().await.unwrap();
+    c.close().await.unwrap();
+
+    assert_all_have(&server, &AUTHORIZATION, "Bearer t0ken");
+    assert_all_have(&server, &project, "fantoccini");
+}
+
+#[test]
+fn rejects_invalid_bearer_token() {
+    assert!(common::builder().bearer_auth("t0ken\nx-evil: 1").is_err());
+}
@@ -0,0 +1,169 @@
+//! WebDriver BiDi transport tests
+#![cfg(feature = "bidi")]
+
+use fantoccini::bidi::{self, BiDi};
+use fantoccini::testing::MockWebDriver;
+use fantoccini::Client;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value as Json};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+mod common;
+
+type Socket = WebSocketStream<TcpStream>;
+
+/// Connect to a mock session whose `webSocketUrl` points at a local WebSocket server, and return
+/// the server's end of the connection.
+async fn connect(server: &MockWebDriver) -> (Client, Socket) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let accept: JoinHandle<Socket> = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio_tungstenite::accept_async(stream).await.unwrap()
+    });
+    server.set_capability("webSocketUrl", json!(url));
+    let c = common::connect_mock(server).await;
+    (c, accept.await.unwrap())
+}
+
+/// Read the next command sent over `ws`.
+async fn next_command(ws: &mut Socket) -> Json {
+    match ws.next().await {
+        Some(Ok(Message::Text(msg))) => serde_json::from_str(&msg).unwrap(),
+        other => panic!("expected a command, got {:?}", other),
+    }
+}
+
+async fn reply(ws: &mut Socket, msg: Json) {
+    ws.send(Message::Text(msg.to_string())).await.unwrap();
+}
+
+fn send(
+    bidi: &BiDi,
+    method: &'static str,
+) -> JoinHandle<Result<Json, fantoccini::error::CmdError>> {
+    let bidi = bidi.clone();
+    tokio::spawn(async move { bidi.send_raw(method, json!({})).await })
+}
+
+#[tokio::test]
+async fn matches_responses_by_id() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (c, mut ws) = connect(&server).await;
+    let bidi = c.bidi().expect("session has a BiDi connection");
+
+    let first = send(bidi, "browsingContext.getTree");
+    let first_cmd = next_command(&mut ws).await;
+    let second = send(bidi, "script.evaluate");
+    let second_cmd = next_command(&mut ws).await;
+    assert_eq!(first_cmd["method"], "browsingContext.getTree");
+    assert_eq!(second_cmd["method"], "script.evaluate");
+    assert_ne!(first_cmd["id"], second_cmd["id"]);
+
+    // answer out of order, with an unrelated id thrown in
+    reply(
+        &mut ws,
+        json!({"type": "success", "id": 9999, "result": "stray"}),
+    )
+    .await;
+    reply(
+        &mut ws,
+        json!({"type": "success", "id": second_cmd["id"], "result": {"n": 2}}),
+    )
+    .await;
+    reply(
+        &mut ws,
+        json!({"type": "success", "id": first_cmd["id"], "result": {"n": 1}}),
+    )
+    .await;
+    assert_eq!(first.await.unwrap().unwrap(), json!({"n": 1}));
+    assert_eq!(second.await.unwrap().unwrap(), json!({"n": 2}));
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn reports_error_replies() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (c, mut ws) = connect(&server).await;
+    let bidi = c.bidi().unwrap();
+
+    let res = send(bidi, "browsingContext.navigate");
+    let cmd = next_command(&mut ws).await;
+    reply(
+        &mut ws,
+        json!({
+            "type": "error",
+            "id": cmd["id"],
+            "error": "no such frame",
+            "message": "context is gone",
+        }),
+    )
+    .await;
+    let err = res.await.unwrap().unwrap_err();
+    assert!(err.is_no_such_frame(), "{:?}", err);
+
+    // a reply that isn't a known error is handed back as is
+    let res = send(bidi, "browsingContext.navigate");
+    let cmd = next_command(&mut ws).await;
+    reply(
+        &mut ws,
+        json!({"type": "error", "id": cmd["id"], "error": "made up"}),
+    )
+    .await;
+    assert!(matches!(
+        res.await.unwrap(),
+        Err(fantoccini::error::CmdError::NotW3C(_))
+    ));
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn broadcasts_events() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (c, mut ws) = connect(&server).await;
+    let bidi = c.bidi().unwrap();
+    let mut a = bidi.events();
+    let mut b = bidi.events();
+
+    reply(
+        &mut ws,
+        json!({
+            "type": "event",
+            "method": "log.entryAdded",
+            "params": {"type": "console", "level": "info", "text": "hi", "timestamp": 1},
+        }),
+    )
+    .await;
+    for events in [&mut a, &mut b] {
+        let event = events.next().await.unwrap();
+        let entry = event.parse::<bidi::LogEntryAdded>().unwrap().unwrap();
+        assert_eq!(entry.text.as_deref(), Some("hi"));
+    }
+
+    c.close().await.unwrap();
+    assert!(a.next().await.is_none());
+    assert!(b.next().await.is_none());
+}
+
+#[tokio::test]
+async fn close_closes_the_socket() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (c, mut ws) = connect(&server).await;
+    let bidi = c.bidi().unwrap().clone();
+
+    let pending = send(&bidi, "browsingContext.getTree");
+    next_command(&mut ws).await;
+    c.close().await.unwrap();
+
+    assert!(matches!(ws.next().await, Some(Ok(Message::Close(_)))));
+    assert!(matches!(
+        pending.await.unwrap(),
+        Err(fantoccini::error::CmdError::Lost(_))
+    ));
+    assert!(bidi.send_raw("session.status", json!({})).await.is_err());
+}
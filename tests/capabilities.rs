@@ -0,0 +1,86 @@
+//! Session capabilities tests
+
+use fantoccini::options::{PageLoadStrategy, ProxyConfig};
+use fantoccini::testing::MockWebDriver;
+use fantoccini::wd::{Capabilities, SessionCapabilities};
+use serde_json::json;
+use std::time::Duration;
+
+mod common;
+
+#[tokio::test]
+async fn typed_capabilities() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("firefox"));
+    server.set_capability("browserVersion", json!("128.0"));
+    server.set_capability("platformName", json!("linux"));
+    server.set_capability("acceptInsecureCerts", json!(false));
+    server.set_capability("pageLoadStrategy", json!("eager"));
+    server.set_capability(
+        "proxy",
+        json!({ "proxyType": "manual", "httpProxy": "127.0.0.1:8080" }),
+    );
+    server.set_capability("setWindowRect", json!(true));
+    server.set_capability(
+        "timeouts",
+        json!({ "implicit": 0, "pageLoad": 300000, "script": 30000 }),
+    );
+    server.set_capability("strictFileInteractability", json!(false));
+    server.set_capability("unhandledPromptBehavior", json!("dismiss and notify"));
+    server.set_capability("moz:processID", json!(4242));
+
+    let c = common::connect_mock(&server).await;
+
+    let caps = c.session_capabilities().unwrap();
+    assert_eq!(caps.browser_name(), Some("firefox"));
+    assert_eq!(caps.browser_version(), Some("128.0"));
+    assert_eq!(caps.platform_name(), Some("linux"));
+    assert_eq!(caps.accept_insecure_certs(), Some(false));
+    assert_eq!(caps.page_load_strategy(), Some(PageLoadStrategy::Eager));
+    assert_eq!(
+        caps.proxy(),
+        Some(&ProxyConfig::Manual {
+            http: Some("127.0.0.1:8080".to_string()),
+            ssl: None,
+            socks: None,
+            socks_version: None,
+            no_proxy: Vec::new(),
+        })
+    );
+    assert_eq!(caps.set_window_rect(), Some(true));
+    assert_eq!(
+        caps.timeouts().unwrap().page_load(),
+        Some(Duration::from_secs(300))
+    );
+    assert_eq!(caps.strict_file_interactability(), Some(false));
+    assert_eq!(caps.unhandled_prompt_behavior(), Some("dismiss and notify"));
+    assert_eq!(caps.web_socket_url(), None);
+    assert_eq!(caps.extension("moz:processID"), Some(&json!(4242)));
+    assert_eq!(caps.extensions().len(), 1);
+
+    c.close().await.unwrap();
+}
+
+#[test]
+fn unparseable_capabilities_are_skipped() {
+    let mut raw = Capabilities::new();
+    raw.insert("browserName".to_string(), json!(42));
+    raw.insert("pageLoadStrategy".to_string(), json!("whenever"));
+    raw.insert("setWindowRect".to_string(), json!(true));
+    raw.insert("proxy".to_string(), json!({}));
+    raw.insert(
+        "webSocketUrl".to_string(),
+        json!("ws://127.0.0.1:9222/session/1"),
+    );
+
+    let caps = SessionCapabilities::from(&raw);
+    assert_eq!(caps.browser_name(), None);
+    assert_eq!(caps.page_load_strategy(), None);
+    assert_eq!(caps.set_window_rect(), Some(true));
+    assert_eq!(caps.proxy(), None);
+    assert_eq!(
+        caps.web_socket_url().map(|u| u.as_str()),
+        Some("ws://127.0.0.1:9222/session/1")
+    );
+    assert!(caps.extensions().is_empty());
+}
@@ -0,0 +1,72 @@
+//! Record/replay tests
+
+use fantoccini::cassette::{Cassette, Recorder, Replayer};
+use fantoccini::testing::{MockElement, MockWebDriver};
+use fantoccini::{Client, Locator};
+
+mod common;
+
+async fn connect(
+    url: &str,
+    transport: impl fantoccini::wd::Transport + 'static,
+) -> Result<Client, fantoccini::error::NewSessionError> {
+    let mut builder = common::builder();
+    builder.transport(transport).connect(url).await
+}
+
+async fn script(c: &Client) -> Result<(String, String), fantoccini::error::CmdError> {
+    c.goto("http://example.com/").await?;
+    let e = c.find(Locator::Css("#greeting")).await?;
+    let text = e.text().await?;
+    let url = c.current_url().await?.to_string();
+    Ok((text, url))
+}
+
+#[tokio::test]
+async fn records_and_replays() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(vec![MockElement::new("p").id("greeting").text("hi")]);
+
+    let recorder = Recorder::new();
+    let c = connect(&server.url(), recorder.clone()).await.unwrap();
+    let recorded = script(&c).await.unwrap();
+    c.close().await.unwrap();
+
+    let path =
+        std::env::temp_dir().join(format!("fantoccini-cassette-{}.json", std::process::id()));
+    recorder.save(&path).unwrap();
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cassette, recorder.cassette());
+    assert_eq!(cassette.interactions.len(), server.received().len());
+
+    // nothing is listening here any more
+    let url = server.url();
+    drop(server);
+
+    let replayer = Replayer::new(cassette);
+    let c = connect(&url, replayer.clone()).await.unwrap();
+    let replayed = script(&c).await.unwrap();
+    assert_eq!(recorded, replayed);
+
+    // requests that weren't recorded fail, and not as if the connection was lost
+    assert!(matches!(
+        c.title().await,
+        Err(fantoccini::error::CmdError::NotRecorded(ref request)) if request.ends_with("/title")
+    ));
+
+    c.close().await.unwrap();
+    assert!(replayer.remaining().is_empty());
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let path = std::env::temp_dir().join(format!(
+        "fantoccini-cassette-v0-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&path, r#"{"version":0,"interactions":[]}"#).unwrap();
+    let err = Cassette::load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,114 @@
+//! Chrome DevTools Protocol tests
+
+use fantoccini::cdp::NetworkConditions;
+use fantoccini::testing::{MockResponse, MockWebDriver};
+use http::Method;
+use serde_json::{json, Value as Json};
+use std::time::Duration;
+
+mod common;
+
+/// The bodies of the CDP commands the server received, with the vendor of each.
+fn cdp_commands(server: &MockWebDriver) -> Vec<(String, Json)> {
+    server
+        .received()
+        .into_iter()
+        .filter(|cmd| cmd.path.ends_with("/cdp/execute"))
+        .map(|cmd| {
+            let vendor = cmd.path.split('/').nth(3).unwrap().to_string();
+            (vendor, cmd.body.unwrap())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn chrome() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("chrome"));
+    server.set_capability(
+        "goog:chromeOptions",
+        json!({ "debuggerAddress": "localhost:9222" }),
+    );
+    // "png", base64-encoded
+    server.respond(
+        Method::POST,
+        "goog/cdp/execute",
+        MockResponse::ok(json!({ "data": "cG5n" })),
+    );
+    let c = common::connect_mock(&server).await;
+
+    c.cdp_emulate_network_conditions(NetworkConditions {
+        latency: Duration::from_millis(250),
+        download_throughput: Some(1000),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    assert_eq!(c.cdp_capture_screenshot(true).await.unwrap(), b"png");
+
+    assert_eq!(
+        cdp_commands(&server),
+        vec![
+            (
+                "goog".to_string(),
+                json!({
+                    "cmd": "Network.emulateNetworkConditions",
+                    "params": {
+                        "offline": false,
+                        "latency": 250.0,
+                        "downloadThroughput": 1000.0,
+                        "uploadThroughput": -1.0,
+                    },
+                })
+            ),
+            (
+                "goog".to_string(),
+                json!({
+                    "cmd": "Page.captureScreenshot",
+                    "params": { "format": "png", "captureBeyondViewport": true },
+                })
+            ),
+        ]
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn edge() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("msedge"));
+    server.set_capability("ms:edgeOptions", json!({}));
+    server.respond(Method::POST, "ms/cdp/execute", MockResponse::ok(json!({})));
+    let c = common::connect_mock(&server).await;
+
+    c.cdp_set_timezone_override("Europe/Oslo").await.unwrap();
+    c.cdp_set_geolocation_override(59.91, 10.75, 10.0)
+        .await
+        .unwrap();
+
+    let commands = cdp_commands(&server);
+    assert_eq!(commands.len(), 2);
+    assert!(commands.iter().all(|(vendor, _)| vendor == "ms"));
+    assert_eq!(
+        commands[0].1["params"],
+        json!({ "timezoneId": "Europe/Oslo" })
+    );
+    assert_eq!(commands[1].1["cmd"], "Emulation.setGeolocationOverride");
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn not_chromium() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("firefox"));
+    let c = common::connect_mock(&server).await;
+
+    let err = c.cdp("Page.reload", json!({})).await.unwrap_err();
+    assert!(err.is_unsupported_operation(), "{:?}", err);
+    assert!(err.to_string().contains("firefox"), "{}", err);
+    assert!(cdp_commands(&server).is_empty());
+
+    c.close().await.unwrap();
+}
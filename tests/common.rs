@@ -3,6 +3,7 @@
 extern crate fantoccini;
 extern crate futures_util;
 
+use fantoccini::testing::MockWebDriver;
 use fantoccini::{error, Client, ClientBuilder};
 
 use hyper::body::Bytes;
@@ -75,6 +76,31 @@ pub async fn make_client(
     }
 }
 
+/// A client builder for whichever TLS backend the tests are built with.
+pub fn builder() -> ClientBuilder<
+    impl hyper_util::client::legacy::connect::Connect
+        + Clone
+        + Send
+        + Sync
+        + Unpin
+        + std::fmt::Debug
+        + 'static,
+> {
+    #[cfg(feature = "rustls-tls")]
+    let builder = ClientBuilder::rustls().unwrap();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls-tls")))]
+    let builder = ClientBuilder::native();
+    builder
+}
+
+/// Connect to the given mock WebDriver server with default settings.
+pub async fn connect_mock(server: &MockWebDriver) -> Client {
+    builder()
+        .connect(&server.url())
+        .await
+        .expect("failed to connect to mock server")
+}
+
 pub fn make_url(s: &str) -> &'static str {
     match s {
         "firefox" => "http://localhost:4444",
@@ -178,7 +204,7 @@ pub fn setup_server() -> u16 {
             .enable_all()
             .build()
             .unwrap();
-        let _ = rt.block_on(async {
+        rt.block_on(async {
             let (socket_addr, server) = start_server();
             tx.send(socket_addr.port())
                 .expect("To be able to send port");
@@ -195,7 +221,7 @@ fn start_server() -> (
     impl Future<Output = hyper::Result<()>> + 'static,
 ) {
     let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
-    let bind = TcpListener::bind(&socket_addr).unwrap();
+    let bind = TcpListener::bind(socket_addr).unwrap();
     let addr = bind.local_addr().unwrap();
 
     let server = async move {
@@ -0,0 +1,87 @@
+//! Concurrent command tests
+
+use fantoccini::testing::MockWebDriver;
+use fantoccini::Client;
+use std::time::{Duration, Instant};
+
+mod common;
+
+const LATENCY: Duration = Duration::from_millis(200);
+
+async fn connect(server: &MockWebDriver, concurrent: bool) -> Client {
+    let mut builder = common::builder();
+    builder
+        .concurrent_commands(concurrent)
+        .connect(&server.url())
+        .await
+        .expect("failed to connect to mock server")
+}
+
+async fn titles(c: &Client, n: usize) -> Duration {
+    let start = Instant::now();
+    let handles: Vec<_> = (0..n)
+        .map(|_| {
+            let c = c.clone();
+            tokio::spawn(async move { c.title().await })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap().unwrap();
+    }
+    start.elapsed()
+}
+
+#[tokio::test]
+async fn reads_are_pipelined() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, true).await;
+    server.set_latency(LATENCY);
+
+    let elapsed = titles(&c, 5).await;
+    assert!(elapsed < LATENCY * 3, "took {:?}", elapsed);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn reads_are_serialized_by_default() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, false).await;
+    server.set_latency(LATENCY);
+
+    let elapsed = titles(&c, 3).await;
+    assert!(elapsed >= LATENCY * 3, "took {:?}", elapsed);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn navigation_is_ordered() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, true).await;
+    server.set_latency(LATENCY);
+    server.clear_received();
+
+    // goto first reads the current url, and then navigates
+    let nav = {
+        let c = c.clone();
+        tokio::spawn(async move { c.goto("http://example.com/").await })
+    };
+    tokio::time::sleep(LATENCY * 3 / 2).await;
+    assert_eq!(server.received().len(), 2);
+
+    // the navigation is still in flight, so this read must not be sent yet
+    let read = {
+        let c = c.clone();
+        tokio::spawn(async move { c.current_url().await })
+    };
+    tokio::time::sleep(LATENCY / 4).await;
+    assert_eq!(server.received().len(), 2);
+
+    nav.await.unwrap().unwrap();
+    let url = read.await.unwrap().unwrap();
+    assert_eq!(url.as_str(), "http://example.com/");
+    assert_eq!(server.received().len(), 3);
+
+    c.close().await.unwrap();
+}
@@ -0,0 +1,85 @@
+//! Detach/attach tests
+
+use fantoccini::testing::MockWebDriver;
+use fantoccini::wd::{SessionDescriptor, TimeoutConfiguration};
+use fantoccini::Client;
+use http::Method;
+use std::time::Duration;
+
+mod common;
+
+async fn detached(server: &MockWebDriver) -> (SessionDescriptor, Client) {
+    let c = common::builder().connect(&server.url()).await.unwrap();
+    c.set_ua("fantoccini-test").await.unwrap();
+    c.update_timeouts(TimeoutConfiguration::new(
+        None,
+        None,
+        Some(Duration::from_secs(2)),
+    ))
+    .await
+    .unwrap();
+    c.goto("http://example.com/").await.unwrap();
+    let clone = c.clone();
+    (c.detach().await.unwrap(), clone)
+}
+
+#[tokio::test]
+async fn detach_and_attach() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (descriptor, old) = detached(&server).await;
+    let caps = old.capabilities().cloned();
+    drop(old);
+
+    // the session outlives the client
+    assert_eq!(server.sessions(), vec![descriptor.session_id().to_string()]);
+    assert_eq!(descriptor.webdriver(), server.url());
+    assert_eq!(descriptor.user_agent(), Some("fantoccini-test"));
+    assert_eq!(
+        descriptor.timeouts().unwrap().implicit(),
+        Some(Duration::from_secs(2))
+    );
+    assert_eq!(descriptor.capabilities(), caps.as_ref());
+
+    let json = serde_json::to_string(&descriptor).unwrap();
+    let descriptor: SessionDescriptor = serde_json::from_str(&json).unwrap();
+
+    let c = common::builder().attach(&descriptor).await.unwrap();
+    assert_eq!(
+        c.session_id().await.unwrap().as_deref(),
+        Some(descriptor.session_id())
+    );
+    assert_eq!(c.capabilities(), descriptor.capabilities());
+    assert_eq!(
+        c.get_ua().await.unwrap().as_deref(),
+        Some("fantoccini-test")
+    );
+    assert_eq!(
+        c.current_url().await.unwrap().as_str(),
+        "http://example.com/"
+    );
+
+    c.close().await.unwrap();
+    assert!(server.sessions().is_empty());
+}
+
+#[tokio::test]
+async fn attach_checks_session() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (descriptor, _) = detached(&server).await;
+    server.expire_session(descriptor.session_id());
+    server.clear_received();
+
+    let err = common::builder().attach(&descriptor).await.unwrap_err();
+    assert!(
+        matches!(err, fantoccini::error::NewSessionError::UnexpectedError(ref e) if e.is_invalid_session_id()),
+        "{:?}",
+        err
+    );
+
+    // give a stray delete a chance to show up
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(server
+        .received()
+        .iter()
+        .all(|cmd| cmd.method != Method::DELETE));
+}
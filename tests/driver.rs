@@ -0,0 +1,231 @@
+//! Tests for launching a local driver process.
+//!
+//! This test binary doubles as the fake driver that it launches: when it is run with a
+//! `--port=<port>` argument, it serves the mock WebDriver server on that port instead of running
+//! the tests. It therefore uses its own `main` rather than the standard test harness, which
+//! understands the test filters, `--exact`, `--skip` and `--list`.
+
+use fantoccini::driver::DriverService;
+use fantoccini::error::{CmdError, NewSessionError};
+use fantoccini::testing::MockWebDriver;
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+mod common;
+
+type Test = fn() -> Pin<Box<dyn Future<Output = ()>>>;
+
+const TESTS: &[(&str, Test)] = &[
+    ("starts_and_stops", || Box::pin(starts_and_stops())),
+    ("stops_when_client_is_dropped", || {
+        Box::pin(stops_when_client_is_dropped())
+    }),
+    ("reports_early_exit", || Box::pin(reports_early_exit())),
+    ("times_out_when_unresponsive", || {
+        Box::pin(times_out_when_unresponsive())
+    }),
+    ("reports_missing_binary", || {
+        Box::pin(reports_missing_binary())
+    }),
+];
+
+fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    if let Some(port) = args.iter().find_map(|arg| arg.strip_prefix("--port=")) {
+        let port = port.parse().unwrap();
+        let has = |flag: &str| args.iter().any(|arg| arg == flag);
+        rt.block_on(fake_driver(port, has("--fail"), has("--hang")));
+        return;
+    }
+
+    // understand the libtest arguments that select tests, and ignore the rest
+    let mut filters = Vec::new();
+    let mut skips = Vec::new();
+    let (mut list, mut exact, mut ignored) = (false, false, false);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--list" => list = true,
+            "--exact" => exact = true,
+            "--ignored" => ignored = true,
+            "--skip" => skips.extend(args.next()),
+            "--test-threads" | "--format" | "--color" | "--logfile" | "-Z" => {
+                args.next();
+            }
+            arg if arg.starts_with('-') => {}
+            filter => filters.push(filter),
+        }
+    }
+    let matches = |name: &str, pattern: &str| {
+        if exact {
+            name == pattern
+        } else {
+            name.contains(pattern)
+        }
+    };
+    let selected: Vec<_> = TESTS
+        .iter()
+        // none of the tests are ignored
+        .filter(|_| !ignored)
+        .filter(|(name, _)| filters.is_empty() || filters.iter().any(|f| matches(name, f)))
+        .filter(|(name, _)| !skips.iter().any(|s| matches(name, s)))
+        .collect();
+
+    if list {
+        for (name, _) in &selected {
+            println!("{}: test", name);
+        }
+        return;
+    }
+    let plural = if selected.len() == 1 { "" } else { "s" };
+    println!("\nrunning {} test{}", selected.len(), plural);
+    for (name, test) in &selected {
+        rt.block_on(test());
+        println!("test {} ... ok", name);
+    }
+    println!(
+        "\ntest result: ok. {} passed; 0 failed; 0 ignored; 0 measured; {} filtered out\n",
+        selected.len(),
+        TESTS.len() - selected.len()
+    );
+}
+
+async fn fake_driver(port: u16, fail: bool, hang: bool) {
+    println!("fake driver starting on port {}", port);
+    eprintln!("fake driver diagnostics");
+    if fail {
+        std::process::exit(3);
+    }
+    if hang {
+        // accept connections, but never answer on them
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        let mut held = Vec::new();
+        loop {
+            held.push(listener.accept().await.unwrap());
+        }
+    }
+    // make the service wait for us
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let _server = MockWebDriver::start_on(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .await
+        .unwrap();
+    std::future::pending::<()>().await;
+}
+
+fn fake() -> (DriverService, Arc<Mutex<Vec<String>>>) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut service = DriverService::new(std::env::current_exe().unwrap());
+    let sink = Arc::clone(&log);
+    service
+        .log_to(move |line| sink.lock().unwrap().push(line.to_string()))
+        .startup_timeout(Duration::from_secs(10));
+    (service, log)
+}
+
+/// The port the fake driver said it was started on.
+fn port(log: &Mutex<Vec<String>>) -> u16 {
+    let log = log.lock().unwrap();
+    let line = log
+        .iter()
+        .find_map(|line| line.strip_prefix("fake driver starting on port "))
+        .expect("driver output was not forwarded");
+    line.parse().unwrap()
+}
+
+/// Wait for the driver on `port` to go away.
+async fn assert_stops(port: u16) {
+    let start = Instant::now();
+    while TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_ok() {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "driver was not stopped"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+async fn starts_and_stops() {
+    let (service, log) = fake();
+    let builder = service.start(common::builder()).await.unwrap();
+    let c = builder.connect_driver().await.unwrap();
+    drop(builder);
+    assert!(c.status().await.unwrap().ready);
+    c.goto("http://example.com/").await.unwrap();
+
+    let port = port(&log);
+    assert!(log
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|line| line == "fake driver diagnostics"));
+
+    c.close().await.unwrap();
+    assert_stops(port).await;
+}
+
+async fn stops_when_client_is_dropped() {
+    let (service, log) = fake();
+    let c = service
+        .start(common::builder())
+        .await
+        .unwrap()
+        .connect_driver()
+        .await
+        .unwrap();
+    let port = port(&log);
+
+    // the driver stays up as long as any client is around
+    let c2 = c.clone();
+    drop(c);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    c2.current_url().await.unwrap();
+
+    drop(c2);
+    assert_stops(port).await;
+}
+
+async fn reports_early_exit() {
+    let (mut service, _) = fake();
+    let err = service
+        .arg("--fail")
+        .start(common::builder())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("exited during startup"), "{}", err);
+
+    // a builder that did not come from a service has no driver to connect to
+    assert!(matches!(
+        common::builder().connect_driver().await,
+        Err(NewSessionError::UnexpectedError(CmdError::InvalidArgument(
+            ..
+        )))
+    ));
+}
+
+async fn times_out_when_unresponsive() {
+    let (mut service, _) = fake();
+    let start = Instant::now();
+    let err = service
+        .arg("--hang")
+        .startup_timeout(Duration::from_millis(500))
+        .start(common::builder())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut, "{}", err);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+async fn reports_missing_binary() {
+    let err = DriverService::new("/nonexistent/fantoccini-test-driver")
+        .start(common::builder())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,104 @@
+//! geckodriver command tests
+
+use fantoccini::firefox::{Addon, Context};
+use fantoccini::testing::{MockResponse, MockWebDriver};
+use http::Method;
+use serde_json::{json, Value as Json};
+
+mod common;
+
+/// The method and body of the requests to `moz/` endpoints that the server received.
+fn moz_requests(server: &MockWebDriver, path: &str) -> Vec<(Method, Option<Json>)> {
+    server
+        .received()
+        .into_iter()
+        .filter(|cmd| cmd.path.ends_with(&format!("/moz/{}", path)))
+        .map(|cmd| (cmd.method, cmd.body))
+        .collect()
+}
+
+#[tokio::test]
+async fn addons() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.respond(
+        Method::POST,
+        "moz/addon/install",
+        MockResponse::ok(json!("test@example.com")),
+    );
+    server.respond(
+        Method::POST,
+        "moz/addon/uninstall",
+        MockResponse::ok(Json::Null),
+    );
+    let c = common::connect_mock(&server).await;
+
+    let id = c
+        .install_addon(Addon::Bytes(b"xpi".to_vec()), true)
+        .await
+        .unwrap();
+    assert_eq!(id, "test@example.com");
+    c.install_addon(Addon::Path("/tmp/ext.xpi".into()), false)
+        .await
+        .unwrap();
+    c.uninstall_addon(&id).await.unwrap();
+
+    assert_eq!(
+        moz_requests(&server, "addon/install"),
+        vec![
+            (
+                Method::POST,
+                Some(json!({ "addon": "eHBp", "temporary": true }))
+            ),
+            (
+                Method::POST,
+                Some(json!({ "path": "/tmp/ext.xpi", "temporary": false }))
+            ),
+        ]
+    );
+    assert_eq!(
+        moz_requests(&server, "addon/uninstall"),
+        vec![(Method::POST, Some(json!({ "id": "test@example.com" })))]
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn full_screenshot() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.respond(
+        Method::GET,
+        "moz/screenshot/full",
+        MockResponse::ok(json!("cG5n")),
+    );
+    let c = common::connect_mock(&server).await;
+
+    assert_eq!(c.full_screenshot().await.unwrap(), b"png");
+    assert_eq!(moz_requests(&server, "screenshot/full").len(), 1);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn context() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.respond(
+        Method::GET,
+        "moz/context",
+        MockResponse::ok(json!("content")),
+    );
+    server.respond(Method::POST, "moz/context", MockResponse::ok(Json::Null));
+    let c = common::connect_mock(&server).await;
+
+    assert_eq!(c.firefox_context().await.unwrap(), Context::Content);
+    c.set_firefox_context(Context::Chrome).await.unwrap();
+    assert_eq!(
+        moz_requests(&server, "context"),
+        vec![
+            (Method::GET, None),
+            (Method::POST, Some(json!({ "context": "chrome" })))
+        ]
+    );
+
+    c.close().await.unwrap();
+}
@@ -0,0 +1,127 @@
+//! Capability negotiation tests
+
+use fantoccini::options::{BrowserOptions, ChromeOptions, FirefoxOptions, PageLoadStrategy};
+use fantoccini::testing::MockWebDriver;
+use fantoccini::wd::Capabilities;
+use fantoccini::Client;
+use http::Method;
+use serde_json::{json, Value as Json};
+
+mod common;
+
+async fn connect(server: &MockWebDriver, first_match: Vec<Capabilities>) -> (Client, Json) {
+    let mut builder = common::builder();
+    let mut always = Capabilities::new();
+    always.insert("acceptInsecureCerts".to_string(), json!(true));
+    let c = builder
+        .capabilities(always)
+        .first_match(first_match)
+        .connect(&server.url())
+        .await
+        .unwrap();
+
+    let body = server
+        .received()
+        .into_iter()
+        .find(|cmd| cmd.method == Method::POST && cmd.path == "/session")
+        .and_then(|cmd| cmd.body)
+        .expect("no new session request");
+    (c, body["capabilities"].clone())
+}
+
+#[tokio::test]
+async fn reports_matched_alternative() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("chrome"));
+    server.set_capability("browserVersion", json!("126.0.6478.126"));
+
+    let (c, caps) = connect(
+        &server,
+        vec![
+            FirefoxOptions::new().headless().to_capabilities(),
+            ChromeOptions::new().headless().to_capabilities(),
+        ],
+    )
+    .await;
+    assert_eq!(c.matched_alternative(), Some(1));
+    assert_eq!(
+        c.session_creation_response().unwrap().matched_alternative(),
+        Some(1)
+    );
+
+    // defaults only go where they do not clash with the alternatives
+    assert_eq!(
+        caps["alwaysMatch"],
+        json!({ "acceptInsecureCerts": true, "pageLoadStrategy": "normal" })
+    );
+    assert_eq!(caps["firstMatch"][0]["browserName"], "firefox");
+    assert_eq!(
+        caps["firstMatch"][1]["goog:chromeOptions"],
+        json!({ "args": ["--headless"], "w3c": true })
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn defaults_follow_alternatives() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("firefox"));
+
+    let mut firefox = FirefoxOptions::new();
+    firefox.page_load_strategy(PageLoadStrategy::Eager);
+    let mut ie = Capabilities::new();
+    ie.insert("browserName".to_string(), json!("internet explorer"));
+
+    let (c, caps) = connect(&server, vec![ie, firefox.to_capabilities()]).await;
+    assert_eq!(c.matched_alternative(), Some(1));
+
+    assert_eq!(caps["alwaysMatch"], json!({ "acceptInsecureCerts": true }));
+    assert_eq!(
+        caps["firstMatch"][0],
+        json!({ "browserName": "internet explorer", "pageLoadStrategy": "normal" })
+    );
+    assert_eq!(caps["firstMatch"][1]["pageLoadStrategy"], "eager");
+    assert_eq!(
+        caps["firstMatch"][1]["goog:chromeOptions"],
+        json!({ "w3c": true })
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn no_alternatives() {
+    let server = MockWebDriver::start().await.unwrap();
+    let (c, caps) = connect(&server, Vec::new()).await;
+    assert_eq!(c.matched_alternative(), None);
+    assert_eq!(caps["firstMatch"], json!([{}]));
+    assert_eq!(
+        caps["alwaysMatch"]["goog:chromeOptions"],
+        json!({ "w3c": true })
+    );
+    c.close().await.unwrap();
+
+    // nor when none of them fits what the remote end returned
+    let (c, _) = connect(&server, vec![ChromeOptions::new().to_capabilities()]).await;
+    assert_eq!(c.matched_alternative(), None);
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn ambiguous_alternatives() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_capability("browserName", json!("chrome"));
+
+    // the remote end does not echo back vendor-prefixed options, so these look the same
+    let (c, _) = connect(
+        &server,
+        vec![
+            ChromeOptions::new().headless().to_capabilities(),
+            ChromeOptions::new().arg("--incognito").to_capabilities(),
+        ],
+    )
+    .await;
+    assert_eq!(c.matched_alternative(), None);
+    c.close().await.unwrap();
+}
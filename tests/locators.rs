@@ -0,0 +1,232 @@
+//! Locator tests
+
+use fantoccini::elements::{Element, ElementRef};
+use fantoccini::error::{CmdError, ErrorStatus};
+use fantoccini::testing::{MockElement, MockResponse, MockWebDriver};
+use fantoccini::Locator;
+use http::Method;
+use serde_json::{json, Value as Json};
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("body").child(
+        MockElement::new("form")
+            .id("signup")
+            .child(MockElement::new("input").attr("name", "email"))
+            .child(MockElement::new("button").class("primary").text("Save"))
+            .child(MockElement::new("button").class("secondary").text("Cancel"))
+            .child(
+                MockElement::new("a")
+                    .attr("href", "/terms")
+                    .text("Terms of service"),
+            ),
+    )]
+}
+
+/// The ids of the given elements.
+fn ids(elements: &[Element]) -> Vec<ElementRef> {
+    elements.iter().map(Element::element_id).collect()
+}
+
+/// The `using` and `value` of the last element search that the server received.
+fn last_search(server: &MockWebDriver) -> (String, String) {
+    let body = server
+        .received()
+        .into_iter()
+        .rev()
+        .find(|cmd| cmd.path.ends_with("/element") || cmd.path.ends_with("/elements"))
+        .and_then(|cmd| cmd.body)
+        .unwrap();
+    (
+        body["using"].as_str().unwrap().to_string(),
+        body["value"].as_str().unwrap().to_string(),
+    )
+}
+
+/// The arguments of the last script that the server executed.
+fn last_script_args(server: &MockWebDriver) -> Json {
+    server
+        .received()
+        .into_iter()
+        .rev()
+        .find(|cmd| cmd.path.ends_with("/execute/sync"))
+        .and_then(|cmd| cmd.body)
+        .unwrap()["args"]
+        .clone()
+}
+
+#[tokio::test]
+async fn native_strategies() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let link = c.find(Locator::PartialLinkText("Terms")).await.unwrap();
+    assert_eq!(link.attr("href").await.unwrap().as_deref(), Some("/terms"));
+    assert_eq!(last_search(&server).0, "partial link text");
+
+    let form = c.find(Locator::Id("signup")).await.unwrap();
+    let buttons = form.find_all(Locator::TagName("button")).await.unwrap();
+    assert_eq!(buttons.len(), 2);
+    assert_eq!(last_search(&server).0, "tag name");
+
+    let email = form.find(Locator::Name("email")).await.unwrap();
+    assert_eq!(email.tag_name().await.unwrap(), "input");
+    assert_eq!(
+        last_search(&server),
+        ("css selector".to_string(), r#"[name="email"]"#.to_string())
+    );
+
+    let cancel = c.find(Locator::ClassName("secondary")).await.unwrap();
+    assert_eq!(cancel.text().await.unwrap(), "Cancel");
+    assert_eq!(
+        last_search(&server),
+        ("css selector".to_string(), ".secondary".to_string())
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn escapes_names_and_classes() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = common::connect_mock(&server).await;
+
+    let _ = c.find(Locator::Name(r#"a"b\c"#)).await;
+    assert_eq!(last_search(&server).1, r#"[name="a\"b\\c"]"#);
+    let _ = c.find(Locator::ClassName("1col md:w-1/2")).await;
+    assert_eq!(last_search(&server).1, r".\31 col\ md\:w-1\/2");
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn text() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let form = c.find(Locator::Id("signup")).await.unwrap();
+    let save = form.find(Locator::ClassName("primary")).await.unwrap();
+    server.on_script("normalize(el.textContent)", json!([save]));
+
+    let found = c
+        .find(Locator::Text {
+            text: "Save",
+            exact: true,
+        })
+        .await
+        .unwrap();
+    assert_eq!(found.element_id(), save.element_id());
+    assert_eq!(last_script_args(&server), json!([null, "Save", true]));
+
+    let found = form
+        .find_all(Locator::Text {
+            text: "Sa",
+            exact: false,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids(&found), vec![save.element_id()]);
+    assert_eq!(last_script_args(&server), json!([form, "Sa", false]));
+
+    let found = c
+        .wait()
+        .for_element(Locator::Text {
+            text: "Save",
+            exact: true,
+        })
+        .await
+        .unwrap();
+    assert_eq!(found.element_id(), save.element_id());
+
+    server.on_script("normalize(el.textContent)", json!([]));
+    let err = c
+        .find(Locator::Text {
+            text: "Missing",
+            exact: true,
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CmdError::Standard(w) if w.error == ErrorStatus::NoSuchElement));
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn role() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let save = c.find(Locator::ClassName("primary")).await.unwrap();
+    let cancel = c.find(Locator::ClassName("secondary")).await.unwrap();
+    server.on_script(
+        "implicitRole",
+        json!([[save, "Save"], [cancel, "  Cancel\n"]]),
+    );
+
+    // the mock has no computed roles or labels, so the script's approximation is used
+    let all = c
+        .find_all(Locator::Role {
+            role: "button",
+            name: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids(&all), vec![save.element_id(), cancel.element_id()]);
+    assert_eq!(last_script_args(&server), json!([null, "button"]));
+    let found = c
+        .find(Locator::Role {
+            role: "button",
+            name: Some("Cancel"),
+        })
+        .await
+        .unwrap();
+    assert_eq!(found.element_id(), cancel.element_id());
+
+    // but the driver's computed role and label take precedence
+    server.respond(
+        Method::GET,
+        "element/*/computedrole",
+        MockResponse::ok(json!("button")),
+    );
+    server.respond(
+        Method::GET,
+        "element/*/computedlabel",
+        MockResponse::ok(json!("Dismiss")),
+    );
+    let err = c
+        .find(Locator::Role {
+            role: "button",
+            name: Some("Cancel"),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CmdError::Standard(w) if w.error == ErrorStatus::NoSuchElement));
+    let found = c
+        .find(Locator::Role {
+            role: "button",
+            name: Some("Dismiss"),
+        })
+        .await
+        .unwrap();
+    assert_eq!(found.element_id(), save.element_id());
+
+    server.respond(
+        Method::GET,
+        "element/*/computedrole",
+        MockResponse::ok(json!("link")),
+    );
+    let all = c
+        .find_all(Locator::Role {
+            role: "button",
+            name: None,
+        })
+        .await
+        .unwrap();
+    assert!(all.is_empty());
+
+    c.close().await.unwrap();
+}
@@ -0,0 +1,165 @@
+//! Middleware tests
+
+use fantoccini::testing::MockWebDriver;
+use fantoccini::wd::{CommandFuture, CommandRequest, Middleware, NextCommand};
+use fantoccini::Client;
+use http::{HeaderValue, Method};
+use serde_json::{json, Value as Json};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+/// Lets a test keep a handle to a middleware it has given to the builder.
+#[derive(Debug)]
+struct Shared(Arc<dyn Middleware>);
+
+impl Middleware for Shared {
+    fn call(&self, request: CommandRequest, next: NextCommand) -> CommandFuture {
+        self.0.call(request, next)
+    }
+}
+
+async fn connect(server: &MockWebDriver, stack: &[Arc<dyn Middleware>]) -> Client {
+    let mut builder = common::builder();
+    for middleware in stack {
+        builder.middleware(Shared(Arc::clone(middleware)));
+    }
+    builder
+        .connect(&server.url())
+        .await
+        .expect("failed to connect to mock server")
+}
+
+/// Tags each request with a header, and logs what it sees.
+#[derive(Debug)]
+struct Log {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Middleware for Log {
+    fn call(&self, mut request: CommandRequest, next: NextCommand) -> CommandFuture {
+        let path = request.request.url.path().to_string();
+        self.log.lock().unwrap().push(format!(
+            "{} > {} {}",
+            self.name, request.request.method, path
+        ));
+        request
+            .request
+            .headers
+            .append("x-layer", HeaderValue::from_static(self.name));
+
+        let name = self.name;
+        let log = Arc::clone(&self.log);
+        let fut = next.run(request);
+        Box::pin(async move {
+            let res = fut.await;
+            let value = res.as_ref().map(Json::to_string).unwrap_or_default();
+            log.lock().unwrap().push(format!("{} < {}", name, value));
+            res
+        })
+    }
+}
+
+#[tokio::test]
+async fn sees_commands_and_results_in_order() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_title("layered");
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let stack: Vec<Arc<dyn Middleware>> = vec![
+        Arc::new(Log {
+            name: "outer",
+            log: Arc::clone(&log),
+        }),
+        Arc::new(Log {
+            name: "inner",
+            log: Arc::clone(&log),
+        }),
+    ];
+    let c = connect(&server, &stack).await;
+    let sid = c.session_id().await.unwrap().unwrap();
+    log.lock().unwrap().clear();
+    server.clear_received();
+
+    assert_eq!(c.title().await.unwrap(), "layered");
+    let path = format!("/session/{}/title", sid);
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            format!("outer > GET {}", path),
+            format!("inner > GET {}", path),
+            r#"inner < "layered""#.to_string(),
+            r#"outer < "layered""#.to_string(),
+        ]
+    );
+
+    let received = server.received();
+    assert_eq!(received.len(), 1);
+    let layers: Vec<_> = received[0].headers.get_all("x-layer").iter().collect();
+    assert_eq!(layers, ["outer", "inner"]);
+
+    c.close().await.unwrap();
+}
+
+/// Sends title requests to the current url endpoint instead.
+#[derive(Debug)]
+struct Rewrite;
+
+impl Middleware for Rewrite {
+    fn call(&self, mut request: CommandRequest, next: NextCommand) -> CommandFuture {
+        let url = &mut request.request.url;
+        if let Some(path) = url.path().strip_suffix("/title") {
+            let path = format!("{}/url", path);
+            url.set_path(&path);
+        }
+        next.run(request)
+    }
+}
+
+#[tokio::test]
+async fn can_rewrite_requests() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, &[Arc::new(Rewrite)]).await;
+    c.goto("http://example.com/").await.unwrap();
+    server.clear_received();
+
+    assert_eq!(c.title().await.unwrap(), "http://example.com/");
+    let received = server.received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].method, Method::GET);
+    assert!(received[0].path.ends_with("/url"));
+
+    c.close().await.unwrap();
+}
+
+/// Answers read-only commands without asking the server.
+#[derive(Debug)]
+struct Cached;
+
+impl Middleware for Cached {
+    fn call(&self, request: CommandRequest, next: NextCommand) -> CommandFuture {
+        if request.command().is_read_only() {
+            Box::pin(async { Ok(json!("cached")) })
+        } else {
+            next.run(request)
+        }
+    }
+}
+
+#[tokio::test]
+async fn can_short_circuit() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, &[Arc::new(Cached)]).await;
+    server.clear_received();
+
+    assert_eq!(c.title().await.unwrap(), "cached");
+    assert!(server.received().is_empty());
+
+    c.refresh().await.unwrap();
+    let received = server.received();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].method, Method::POST);
+    assert!(received[0].path.ends_with("/refresh"));
+
+    c.close().await.unwrap();
+}
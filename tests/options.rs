@@ -0,0 +1,97 @@
+//! Browser options tests
+
+use fantoccini::options::{
+    BrowserOptions, ChromeOptions, ChromiumLogLevel, FirefoxLogLevel, FirefoxOptions,
+    PageLoadStrategy,
+};
+use fantoccini::testing::MockWebDriver;
+use fantoccini::wd::Capabilities;
+use http::Method;
+use serde_json::{json, Value as Json};
+
+mod common;
+
+async fn new_session_body(caps: Capabilities) -> Json {
+    new_session_body_with(caps, Vec::new()).await
+}
+
+async fn new_session_body_with(caps: Capabilities, first_match: Vec<Capabilities>) -> Json {
+    let server = MockWebDriver::start().await.unwrap();
+    let mut builder = common::builder();
+    let c = builder
+        .capabilities(caps)
+        .first_match(first_match)
+        .connect(&server.url())
+        .await
+        .unwrap();
+    c.close().await.unwrap();
+
+    server
+        .received()
+        .into_iter()
+        .find(|cmd| cmd.method == Method::POST && cmd.path == "/session")
+        .and_then(|cmd| cmd.body)
+        .expect("no new session request")
+}
+
+#[tokio::test]
+async fn chrome_keeps_w3c() {
+    let caps = ChromeOptions::new()
+        .headless()
+        .arg("--disable-gpu")
+        .to_capabilities();
+    let body = new_session_body(caps).await;
+    let caps = &body["capabilities"]["alwaysMatch"];
+    assert_eq!(caps["browserName"], "chrome");
+    assert_eq!(caps["pageLoadStrategy"], "normal");
+    assert_eq!(
+        caps["goog:chromeOptions"],
+        json!({ "w3c": true, "args": ["--headless", "--disable-gpu"] })
+    );
+}
+
+#[tokio::test]
+async fn page_load_strategy_is_kept() {
+    let caps = FirefoxOptions::new()
+        .headless()
+        .page_load_strategy(PageLoadStrategy::Eager)
+        .to_capabilities();
+    let body = new_session_body(caps).await;
+    let caps = &body["capabilities"]["alwaysMatch"];
+    assert_eq!(caps["browserName"], "firefox");
+    assert_eq!(caps["pageLoadStrategy"], "eager");
+    assert_eq!(caps["moz:firefoxOptions"]["args"], json!(["--headless"]));
+}
+
+#[tokio::test]
+async fn merged_into_alternatives() {
+    let chrome = ChromeOptions::new()
+        .headless()
+        .log_level(ChromiumLogLevel::Error)
+        .page_load_strategy(PageLoadStrategy::Eager)
+        .to_capabilities();
+    let firefox = FirefoxOptions::new()
+        .headless()
+        .log_level(FirefoxLogLevel::Debug)
+        .to_capabilities();
+    let body = new_session_body_with(Capabilities::new(), vec![chrome, firefox]).await;
+    let caps = &body["capabilities"];
+
+    // the options stay in their alternatives, and so do the defaults for keys they set
+    assert!(caps["alwaysMatch"].get("goog:chromeOptions").is_none());
+    assert!(caps["alwaysMatch"].get("pageLoadStrategy").is_none());
+    let chrome = &caps["firstMatch"][0];
+    assert_eq!(chrome["browserName"], "chrome");
+    assert_eq!(chrome["pageLoadStrategy"], "eager");
+    assert_eq!(
+        chrome["goog:chromeOptions"],
+        json!({ "w3c": true, "args": ["--headless", "--log-level=2"] })
+    );
+    let firefox = &caps["firstMatch"][1];
+    assert_eq!(firefox["browserName"], "firefox");
+    assert_eq!(firefox["pageLoadStrategy"], "normal");
+    assert_eq!(
+        firefox["moz:firefoxOptions"],
+        json!({ "args": ["--headless"], "log": { "level": "debug" } })
+    );
+}
@@ -0,0 +1,123 @@
+//! Session pool tests
+
+use fantoccini::cookies::Cookie;
+use fantoccini::error::ErrorStatus;
+use fantoccini::pool::SessionPool;
+use fantoccini::testing::{MockResponse, MockWebDriver};
+use fantoccini::wd::TimeoutConfiguration;
+use http::Method;
+use std::time::Duration;
+
+mod common;
+
+fn pool(
+    server: &MockWebDriver,
+    size: usize,
+) -> SessionPool<
+    impl hyper_util::client::legacy::connect::Connect
+        + Clone
+        + Send
+        + Sync
+        + Unpin
+        + std::fmt::Debug
+        + 'static,
+> {
+    SessionPool::new(common::builder(), &server.url(), size)
+}
+
+#[tokio::test]
+async fn creates_sessions_lazily_and_reuses_them() {
+    let server = MockWebDriver::start().await.unwrap();
+    let pool = pool(&server, 2);
+    assert!(server.sessions().is_empty());
+
+    let a = pool.get().await.unwrap();
+    let b = pool.get().await.unwrap();
+    assert_eq!(server.sessions().len(), 2);
+    let a_id = a.session_id().await.unwrap();
+    assert_ne!(a_id, b.session_id().await.unwrap());
+
+    // the pool is exhausted, so the next lease has to wait for a return
+    let next = {
+        let pool = pool.clone();
+        tokio::spawn(async move { pool.get().await.unwrap().session_id().await.unwrap() })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!next.is_finished());
+
+    drop(a);
+    assert_eq!(next.await.unwrap(), a_id);
+    assert_eq!(server.sessions().len(), 2);
+}
+
+#[tokio::test]
+async fn resets_returned_sessions() {
+    let server = MockWebDriver::start().await.unwrap();
+    let pool = pool(&server, 1);
+
+    let c = pool.get().await.unwrap();
+    let timeouts = c.get_timeouts().await.unwrap();
+    let window = c.window().await.unwrap();
+    c.goto("http://example.com/").await.unwrap();
+    c.add_cookie(Cookie::new("visited", "yes")).await.unwrap();
+    c.new_window(true).await.unwrap();
+    c.update_timeouts(TimeoutConfiguration::new(
+        None,
+        None,
+        Some(Duration::from_secs(5)),
+    ))
+    .await
+    .unwrap();
+    drop(c);
+
+    let c = pool.get().await.unwrap();
+    assert_eq!(pool.idle(), 0);
+    assert!(c.get_all_cookies().await.unwrap().is_empty());
+    assert_eq!(c.windows().await.unwrap(), vec![window.clone()]);
+    assert_eq!(c.window().await.unwrap(), window);
+    assert_eq!(c.current_url().await.unwrap().as_str(), "about:blank");
+    assert_eq!(c.get_timeouts().await.unwrap(), timeouts);
+}
+
+#[tokio::test]
+async fn replaces_dead_sessions() {
+    let server = MockWebDriver::start().await.unwrap();
+    let pool = pool(&server, 1);
+
+    let c = pool.get().await.unwrap();
+    let id = c.session_id().await.unwrap().unwrap();
+    drop(c);
+    // wait for the session to be reset and returned
+    while pool.idle() == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    server.expire_session(&id);
+    let c = pool.get().await.unwrap();
+    let new_id = c.session_id().await.unwrap().unwrap();
+    assert_ne!(id, new_id);
+    assert_eq!(server.sessions(), vec![new_id]);
+}
+
+#[tokio::test]
+async fn replaces_sessions_that_fail_the_health_check() {
+    let server = MockWebDriver::start().await.unwrap();
+    let pool = pool(&server, 1);
+
+    let c = pool.get().await.unwrap();
+    let id = c.session_id().await.unwrap().unwrap();
+    drop(c);
+    while pool.idle() == 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    server.respond(
+        Method::GET,
+        "title",
+        MockResponse::error(ErrorStatus::UnexpectedAlertOpen, "user prompt"),
+    );
+    let c = pool.get().await.unwrap();
+    let new_id = c.session_id().await.unwrap().unwrap();
+    assert_ne!(id, new_id);
+    assert_eq!(server.sessions(), vec![new_id]);
+}
@@ -0,0 +1,109 @@
+//! Tests for packaging Firefox profiles, by unpacking the archives they produce.
+
+use base64::Engine;
+use fantoccini::options::{BrowserOptions, FirefoxOptions};
+use fantoccini::profile::FirefoxProfile;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+
+/// A fresh directory under the system's temporary directory.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fantoccini-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn unzip(encoded: &str) -> BTreeMap<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+    let mut files = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        files.insert(file.name().to_string(), contents);
+    }
+    files
+}
+
+#[test]
+fn from_scratch() {
+    let dir = scratch_dir("profile-scratch");
+    let xpi = dir.join("ext.xpi");
+    fs::write(&xpi, "not really a zip").unwrap();
+
+    let mut profile = FirefoxProfile::new();
+    profile
+        .pref("browser.startup.page", 0)
+        .pref("browser.download.dir", "/tmp/downloads")
+        .certificate_override("localhost", 8443, "ab:cd")
+        .extension("test@example.com", &xpi);
+    let files = unzip(&profile.to_base64().unwrap());
+
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [
+            "cert_override.txt",
+            "extensions/test@example.com.xpi",
+            "user.js"
+        ]
+    );
+    assert_eq!(
+        files["user.js"],
+        "user_pref(\"browser.startup.page\", 0);\n\
+         user_pref(\"browser.download.dir\", \"/tmp/downloads\");\n"
+    );
+    assert_eq!(
+        files["cert_override.txt"],
+        "localhost:8443:\tOID.2.16.840.1.101.3.4.2.1\tAB:CD\t\n"
+    );
+    assert_eq!(files["extensions/test@example.com.xpi"], "not really a zip");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn from_existing_dir() {
+    let dir = scratch_dir("profile-existing");
+    fs::write(dir.join("user.js"), "user_pref(\"a\", 1);").unwrap();
+    fs::write(dir.join("prefs.js"), "// prefs").unwrap();
+    fs::write(dir.join("parent.lock"), "").unwrap();
+    fs::create_dir(dir.join("chrome")).unwrap();
+    fs::write(dir.join("chrome").join("userChrome.css"), "* {}").unwrap();
+
+    let mut profile = FirefoxProfile::from_dir(&dir);
+    profile.pref("a", 2);
+    let caps = FirefoxOptions::new()
+        .profile(&profile)
+        .unwrap()
+        .to_capabilities();
+    let files = unzip(caps["moz:firefoxOptions"]["profile"].as_str().unwrap());
+
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        ["chrome/userChrome.css", "prefs.js", "user.js"]
+    );
+    // our preference comes last, and so wins
+    assert_eq!(
+        files["user.js"],
+        "user_pref(\"a\", 1);\nuser_pref(\"a\", 2);\n"
+    );
+    assert_eq!(files["chrome/userChrome.css"], "* {}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_files_are_errors() {
+    let mut profile = FirefoxProfile::from_dir("/nonexistent/fantoccini/profile");
+    assert!(profile.to_zip().is_err());
+
+    profile = FirefoxProfile::new();
+    profile.extension("test@example.com", "/nonexistent/fantoccini/ext.xpi");
+    assert!(FirefoxOptions::new().profile(&profile).is_err());
+}
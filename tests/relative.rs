@@ -0,0 +1,122 @@
+//! Relative locator tests
+
+use fantoccini::elements::Element;
+use fantoccini::relative::RelativeLocator;
+use fantoccini::testing::{MockElement, MockWebDriver};
+use fantoccini::Locator;
+use serde_json::json;
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("body")
+        .child(MockElement::new("label").text("Email"))
+        .child(MockElement::new("input").attr("name", "email"))
+        .child(MockElement::new("input").attr("name", "password"))]
+}
+
+async fn names(elements: Vec<Element>) -> Vec<String> {
+    let mut names = Vec::new();
+    for e in elements {
+        names.push(e.attr("name").await.unwrap().unwrap());
+    }
+    names
+}
+
+#[tokio::test]
+async fn filters_and_sorts_by_position() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+    let label = c.find(Locator::TagName("label")).await.unwrap();
+
+    // the label, then the email input to the right of it, then the password input below it
+    server.on_script(
+        "getBoundingClientRect",
+        json!([[0, 0, 50, 20], [60, 0, 100, 20], [0, 30, 100, 20]]),
+    );
+    let inputs = RelativeLocator::new(Locator::TagName("input"));
+
+    let right = inputs
+        .clone()
+        .right_of(&label)
+        .resolve_all(&c)
+        .await
+        .unwrap();
+    assert_eq!(names(right).await, vec!["email"]);
+    let below = inputs.clone().below(&label).resolve(&c).await.unwrap();
+    assert_eq!(
+        below.attr("name").await.unwrap().as_deref(),
+        Some("password")
+    );
+    assert!(inputs
+        .clone()
+        .above(&label)
+        .resolve_all(&c)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // both are 10px away, but the password input's center is closer to the label's
+    let near = inputs
+        .clone()
+        .near(&label, 15.0)
+        .resolve_all(&c)
+        .await
+        .unwrap();
+    assert_eq!(names(near).await, vec!["password", "email"]);
+    let err = inputs.near(&label, 5.0).resolve(&c).await.unwrap_err();
+    assert!(err.is_no_such_element());
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn combines_conditions() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+    let label = c.find(Locator::TagName("label")).await.unwrap();
+
+    // each anchor is measured once per condition
+    server.on_script(
+        "getBoundingClientRect",
+        json!([
+            [0, 0, 50, 20],
+            [0, 0, 50, 20],
+            [60, 0, 100, 20],
+            [0, 30, 100, 20]
+        ]),
+    );
+    let found = RelativeLocator::new(Locator::TagName("input"))
+        .near(&label, 15.0)
+        .left_of(&label)
+        .resolve_all(&c)
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+
+    // anchors are never among the results
+    server.on_script(
+        "getBoundingClientRect",
+        json!([[0, 0, 50, 20], [60, 0, 100, 20], [0, 30, 100, 20]]),
+    );
+    let email = c.find(Locator::Name("email")).await.unwrap();
+    let found = RelativeLocator::new(Locator::Css("label, input"))
+        .near(&email, 1000.0)
+        .resolve_all(&c)
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+    let args = server
+        .received()
+        .into_iter()
+        .rev()
+        .find(|cmd| cmd.path.ends_with("/execute/sync"))
+        .and_then(|cmd| cmd.body)
+        .unwrap()["args"]
+        .clone();
+    assert_eq!(args[0], json!(email));
+
+    c.close().await.unwrap();
+}
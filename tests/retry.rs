@@ -0,0 +1,82 @@
+//! Retry tests
+
+use fantoccini::testing::{MockElement, MockWebDriver};
+use fantoccini::wd::RetryPolicy;
+use fantoccini::{Client, Locator};
+use http::Method;
+use std::time::Duration;
+
+mod common;
+
+async fn connect(server: &MockWebDriver, policy: Option<RetryPolicy>) -> Client {
+    let mut builder = common::builder();
+    if let Some(policy) = policy {
+        builder.retry_policy(policy);
+    }
+    builder
+        .connect(&server.url())
+        .await
+        .expect("failed to connect to mock server")
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(10))
+}
+
+fn count(server: &MockWebDriver, method: Method, suffix: &str) -> usize {
+    server
+        .received()
+        .iter()
+        .filter(|cmd| cmd.method == method && cmd.path.ends_with(suffix))
+        .count()
+}
+
+#[tokio::test]
+async fn retries_idempotent_commands() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_title("retried");
+    let c = connect(&server, Some(policy())).await;
+
+    server.drop_next(2);
+    assert_eq!(c.title().await.unwrap(), "retried");
+    assert_eq!(count(&server, Method::GET, "/title"), 3);
+
+    // but gives up after max_attempts
+    server.drop_next(3);
+    assert!(c.title().await.is_err());
+    assert_eq!(count(&server, Method::GET, "/title"), 6);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn does_not_retry_by_default() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, None).await;
+
+    server.drop_next(1);
+    assert!(c.title().await.is_err());
+    assert_eq!(count(&server, Method::GET, "/title"), 1);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn non_idempotent_commands_need_opt_in() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(vec![MockElement::new("button").id("b")]);
+
+    let c = connect(&server, Some(policy())).await;
+    let button = c.find(Locator::Id("b")).await.unwrap();
+    server.drop_next(1);
+    assert!(button.click().await.is_err());
+    assert_eq!(count(&server, Method::POST, "/click"), 1);
+    c.close().await.unwrap();
+
+    let c = connect(&server, Some(policy().retry_non_idempotent(true))).await;
+    let button = c.find(Locator::Id("b")).await.unwrap();
+    server.drop_next(1);
+    button.click().await.unwrap();
+    assert_eq!(count(&server, Method::POST, "/click"), 3);
+    c.close().await.unwrap();
+}
@@ -0,0 +1,75 @@
+//! Scoped session tests
+
+use fantoccini::error::CmdError;
+use fantoccini::testing::MockWebDriver;
+use fantoccini::Locator;
+use std::time::{Duration, Instant};
+
+mod common;
+
+#[tokio::test]
+async fn closes_after_success() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_title("scoped");
+    let title = common::builder()
+        .run(&server.url(), |c| async move { c.title().await })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(title, "scoped");
+    assert!(server.sessions().is_empty());
+}
+
+#[tokio::test]
+async fn closes_after_error() {
+    let server = MockWebDriver::start().await.unwrap();
+    let res = common::builder()
+        .run(&server.url(), |c| async move {
+            // even persisted sessions are closed
+            c.persist().await?;
+            c.find(Locator::Id("missing")).await?;
+            Ok::<_, CmdError>(())
+        })
+        .await
+        .unwrap();
+    assert!(res.unwrap_err().is_no_such_element());
+    assert!(server.sessions().is_empty());
+}
+
+#[tokio::test]
+async fn closes_after_panic() {
+    let server = MockWebDriver::start().await.unwrap();
+    let url = server.url();
+    let res = tokio::spawn(async move {
+        common::builder()
+            .run(&url, |c| async move {
+                c.goto("http://example.com/").await.unwrap();
+                panic!("test failed");
+            })
+            .await
+    })
+    .await;
+    assert!(res.unwrap_err().is_panic());
+    assert!(server.sessions().is_empty());
+}
+
+#[tokio::test]
+async fn closes_when_cancelled() {
+    let server = MockWebDriver::start().await.unwrap();
+    let builder = common::builder();
+    let url = server.url();
+    let run = builder.run(&url, |c| async move {
+        c.persist().await.unwrap();
+        std::future::pending::<()>().await
+    });
+    assert!(tokio::time::timeout(Duration::from_millis(200), run)
+        .await
+        .is_err());
+    assert_eq!(server.sessions().len(), 1);
+
+    let start = Instant::now();
+    while !server.sessions().is_empty() {
+        assert!(start.elapsed() < Duration::from_secs(5), "session leaked");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
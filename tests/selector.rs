@@ -0,0 +1,107 @@
+//! Selector tests
+
+use fantoccini::selector::{Selector, Step};
+use fantoccini::testing::{MockElement, MockWebDriver};
+use fantoccini::{Locator, LocatorBuf};
+use http::Method;
+use serde_json::json;
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("body")
+        .child(
+            MockElement::new("form")
+                .id("signup")
+                .child(MockElement::new("input").attr("name", "email"))
+                .child(MockElement::new("input").attr("name", "password")),
+        )
+        .child(MockElement::new("iframe").id("payment"))]
+}
+
+/// A page object that keeps its selectors around.
+struct SignupPage {
+    inputs: Selector,
+    email: Selector,
+}
+
+impl SignupPage {
+    fn new(form_id: &str) -> Self {
+        let form = Selector::new(LocatorBuf::Id(form_id.to_string()));
+        SignupPage {
+            inputs: form.clone().descend(Locator::TagName("input")),
+            email: form.descend(LocatorBuf::Name("email".to_string())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn descends() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let page = SignupPage::new("signup");
+    let email = page.email.resolve(&c).await.unwrap();
+    assert_eq!(email.attr("name").await.unwrap().as_deref(), Some("email"));
+    assert_eq!(page.inputs.resolve_all(&c).await.unwrap().len(), 2);
+    assert_eq!(
+        page.email.steps(),
+        &[
+            Step::Descend(LocatorBuf::Id("signup".to_string())),
+            Step::Descend(LocatorBuf::Name("email".to_string())),
+        ]
+    );
+    assert_eq!(page.email.steps()[1].locator(), Locator::Name("email"));
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn reports_failed_step() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let missing = Selector::new(Locator::Id("signup"))
+        .descend(Locator::Css("select"))
+        .descend(Locator::Css("option"));
+    let err = missing.resolve(&c).await.unwrap_err();
+    assert!(err.is_no_such_element());
+    let message = err.to_string();
+    assert!(message.contains("step 2 of 3"), "{}", message);
+    assert!(message.contains(r#"Css("select")"#), "{}", message);
+
+    // the mock has no shadow roots
+    let pierced = Selector::new(Locator::Id("signup")).pierce(Locator::Css("input"));
+    let err = pierced.resolve_all(&c).await.unwrap_err();
+    assert!(err.is_no_such_shadow_root());
+    assert!(err.to_string().contains("step 2 of 2"), "{}", err);
+    assert_eq!(pierced.to_string(), r#"Id("signup") >>> Css("input")"#);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn hops_into_frames() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+
+    let iframe = c.find(Locator::Id("payment")).await.unwrap();
+    let selector = Selector::new(Locator::Id("payment")).frame(Locator::Name("password"));
+    let found = selector.resolve(&c).await.unwrap();
+    assert_eq!(
+        found.attr("name").await.unwrap().as_deref(),
+        Some("password")
+    );
+
+    let frame = server
+        .received()
+        .into_iter()
+        .find(|cmd| cmd.method == Method::POST && cmd.path.ends_with("/frame"))
+        .unwrap();
+    assert_eq!(frame.body, Some(json!({ "id": iframe })));
+
+    c.close().await.unwrap();
+}
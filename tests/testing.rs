@@ -0,0 +1,136 @@
+//! Mock WebDriver server tests
+
+use fantoccini::error::{CmdError, ErrorStatus};
+use fantoccini::testing::{MockElement, MockResponse, MockWebDriver};
+use fantoccini::Locator;
+use http::Method;
+use serde_json::json;
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("body")
+        .child(
+            MockElement::new("div")
+                .id("content")
+                .class("main")
+                .child(MockElement::new("p").class("intro").text("Hello"))
+                .child(
+                    MockElement::new("a")
+                        .attr("href", "/other")
+                        .text("Other page"),
+                ),
+        )
+        .child(MockElement::new("input").attr("name", "q"))
+        .child(MockElement::new("button").id("gone").hidden())]
+}
+
+#[tokio::test]
+async fn navigates_and_records() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_title("Mock");
+    let c = common::connect_mock(&server).await;
+
+    c.goto("http://example.com/").await.unwrap();
+    assert_eq!(
+        c.current_url().await.unwrap().as_str(),
+        "http://example.com/"
+    );
+    assert_eq!(c.title().await.unwrap(), "Mock");
+    c.back().await.unwrap();
+    assert_eq!(c.current_url().await.unwrap().as_str(), "about:blank");
+    c.forward().await.unwrap();
+    assert_eq!(
+        c.current_url().await.unwrap().as_str(),
+        "http://example.com/"
+    );
+
+    let sid = c.session_id().await.unwrap().unwrap();
+    assert_eq!(server.sessions(), vec![sid.clone()]);
+    c.close().await.unwrap();
+    assert!(server.sessions().is_empty());
+
+    let received = server.received();
+    assert_eq!(received[0].method, Method::POST);
+    assert_eq!(received[0].path, "/session");
+    // goto first fetches the current url to resolve relative urls against
+    assert_eq!(received[1].method, Method::GET);
+    let goto = &received[2];
+    assert_eq!(goto.path, format!("/session/{}/url", sid));
+    assert_eq!(goto.body, Some(json!({ "url": "http://example.com/" })));
+    assert_eq!(received.last().unwrap().method, Method::DELETE);
+}
+
+#[tokio::test]
+async fn finds_and_interacts_with_elements() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+    c.goto("http://example.com/start").await.unwrap();
+
+    let intro = c.find(Locator::Css("div.main > p.intro")).await.unwrap();
+    assert_eq!(intro.text().await.unwrap(), "Hello");
+    assert_eq!(intro.tag_name().await.unwrap(), "p");
+
+    let content = c.find(Locator::Id("content")).await.unwrap();
+    assert_eq!(content.text().await.unwrap(), "HelloOther page");
+    assert_eq!(
+        content.find_all(Locator::Css("p, a")).await.unwrap().len(),
+        2
+    );
+
+    let q = c.find(Locator::Css("input[name='q']")).await.unwrap();
+    q.send_keys("ru").await.unwrap();
+    q.send_keys("st").await.unwrap();
+    assert_eq!(q.prop("value").await.unwrap().as_deref(), Some("rust"));
+
+    let gone = c.find(Locator::Id("gone")).await.unwrap();
+    assert!(!gone.is_displayed().await.unwrap());
+
+    assert!(matches!(
+        c.find(Locator::Css("table")).await,
+        Err(e) if e.is_no_such_element()
+    ));
+    assert!(matches!(
+        c.find(Locator::Css("p[id]é")).await,
+        Err(e) if e.is_invalid_selector()
+    ));
+
+    c.find(Locator::LinkText("Other page"))
+        .await
+        .unwrap()
+        .click()
+        .await
+        .unwrap();
+    assert_eq!(
+        c.current_url().await.unwrap().as_str(),
+        "http://example.com/other"
+    );
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn canned_responses_and_scripts() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.respond(
+        Method::GET,
+        "title",
+        MockResponse::error(ErrorStatus::JavascriptError, "boom"),
+    );
+    server.on_script("return 1 + 1", json!(2));
+    let c = common::connect_mock(&server).await;
+
+    match c.title().await {
+        Err(CmdError::Standard(e)) => {
+            assert_eq!(e.error, ErrorStatus::JavascriptError);
+            assert_eq!(e.message, "boom");
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    assert_eq!(c.execute("return 1 + 1", vec![]).await.unwrap(), json!(2));
+    assert_eq!(c.execute("return 3", vec![]).await.unwrap(), json!(null));
+
+    c.close().await.unwrap();
+}
@@ -0,0 +1,81 @@
+//! Request timeout tests
+
+use fantoccini::error::CmdError;
+use fantoccini::testing::MockWebDriver;
+use fantoccini::Client;
+use std::time::{Duration, Instant};
+
+mod common;
+
+const LATENCY: Duration = Duration::from_millis(300);
+
+async fn connect(server: &MockWebDriver, timeout: Option<Duration>) -> Client {
+    let mut builder = common::builder();
+    if let Some(timeout) = timeout {
+        builder.request_timeout(timeout);
+    }
+    builder
+        .connect(&server.url())
+        .await
+        .expect("failed to connect to mock server")
+}
+
+#[tokio::test]
+async fn default_timeout() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, Some(LATENCY / 3)).await;
+    server.set_latency(LATENCY);
+
+    let start = Instant::now();
+    match c.title().await {
+        Err(CmdError::RequestTimeout(t)) => assert_eq!(t, LATENCY / 3),
+        r => panic!("expected a request timeout, got {:?}", r),
+    }
+    assert!(start.elapsed() < LATENCY, "took {:?}", start.elapsed());
+
+    // which can be lifted for individual commands
+    c.with_timeout(LATENCY * 3).title().await.unwrap();
+
+    server.set_latency(Duration::ZERO);
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn per_call_timeout() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, None).await;
+    server.set_latency(LATENCY);
+
+    let quick = c.with_timeout(LATENCY / 3);
+    assert!(matches!(
+        quick.title().await,
+        Err(CmdError::RequestTimeout(_))
+    ));
+    // the original client is unaffected
+    c.title().await.unwrap();
+
+    server.set_latency(Duration::ZERO);
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn dropping_a_command_cancels_it() {
+    let server = MockWebDriver::start().await.unwrap();
+    let c = connect(&server, None).await;
+    server.set_latency(LATENCY * 3);
+
+    let slow = {
+        let c = c.clone();
+        tokio::spawn(async move { c.title().await })
+    };
+    tokio::time::sleep(LATENCY / 3).await;
+    slow.abort();
+    server.set_latency(Duration::ZERO);
+
+    // the session does not wait for the abandoned request before sending the next one
+    let start = Instant::now();
+    c.current_url().await.unwrap();
+    assert!(start.elapsed() < LATENCY, "took {:?}", start.elapsed());
+
+    c.close().await.unwrap();
+}
@@ -0,0 +1,109 @@
+//! Tracing tests
+#![cfg(feature = "tracing")]
+
+use fantoccini::error::ErrorStatus;
+use fantoccini::testing::{MockResponse, MockWebDriver};
+use http::Method;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+mod common;
+
+type Fields = BTreeMap<String, String>;
+
+/// Collects the fields of every closed span.
+#[derive(Clone, Default)]
+struct Spans(Arc<Mutex<Vec<(String, Fields)>>>);
+
+struct Values(Fields);
+
+impl Visit for Values {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl<S> Layer<S> for Spans
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut values = Values(Fields::new());
+        attrs.record(&mut values);
+        ctx.span(id).unwrap().extensions_mut().insert(values);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        values.record(extensions.get_mut::<Values>().unwrap());
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let fields = span.extensions_mut().remove::<Values>().unwrap().0;
+        self.0
+            .lock()
+            .unwrap()
+            .push((span.name().to_string(), fields));
+    }
+}
+
+#[tokio::test]
+async fn emits_a_span_per_command() {
+    let spans = Spans::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_title("traced");
+    server.respond(
+        Method::GET,
+        "source",
+        MockResponse::error(ErrorStatus::JavascriptError, "boom"),
+    );
+
+    let c = common::connect_mock(&server).await;
+    let sid = c.session_id().await.unwrap().unwrap();
+
+    assert_eq!(c.title().await.unwrap(), "traced");
+    assert!(c.source().await.is_err());
+    c.close().await.unwrap();
+
+    let spans = spans.0.lock().unwrap();
+    let commands: Vec<_> = spans
+        .iter()
+        .filter(|(name, _)| name == "webdriver_command")
+        .map(|(_, fields)| fields)
+        .collect();
+    // new session, title, and source
+    assert_eq!(commands.len(), 3);
+
+    assert_eq!(commands[0]["method"], "POST");
+    assert_eq!(commands[0]["path"], "/session");
+    assert!(!commands[0].contains_key("session_id"));
+
+    let title = commands[1];
+    assert_eq!(title["method"], "GET");
+    assert_eq!(title["path"], format!("/session/{}/title", sid));
+    assert_eq!(title["session_id"], sid);
+    assert_eq!(title["status"], "200");
+    assert!(title.contains_key("duration_ms"));
+    assert!(!title.contains_key("error"));
+
+    let source = commands[2];
+    assert_eq!(source["status"], "500");
+    assert_eq!(source["error_status"], "javascript error");
+    assert_eq!(source["error"], "webdriver returned error: boom");
+}
@@ -0,0 +1,143 @@
+//! File upload tests
+
+use base64::Engine;
+use fantoccini::error::CmdError;
+use fantoccini::testing::{MockElement, MockResponse, MockWebDriver};
+use fantoccini::Locator;
+use http::Method;
+use serde_json::json;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+
+mod common;
+
+fn sample_dom() -> Vec<MockElement> {
+    vec![MockElement::new("form")
+        .child(MockElement::new("input").id("one").attr("type", "file"))
+        .child(
+            MockElement::new("input")
+                .id("many")
+                .attr("type", "file")
+                .attr("multiple", ""),
+        )
+        .child(MockElement::new("input").id("text").attr("type", "text"))]
+}
+
+/// A fresh directory under the system's temporary directory, with two files in it.
+fn scratch_files(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("fantoccini-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a.txt");
+    let b = dir.join("b.txt");
+    fs::write(&a, "first").unwrap();
+    fs::write(&b, "second").unwrap();
+    (fs::canonicalize(a).unwrap(), fs::canonicalize(b).unwrap())
+}
+
+#[tokio::test]
+async fn local_paths() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+    let (a, b) = scratch_files("upload-local");
+
+    let many = c.find(Locator::Id("many")).await.unwrap();
+    many.upload_files(&[&a, &b]).await.unwrap();
+    assert_eq!(
+        many.attr("value").await.unwrap().unwrap(),
+        format!("{}\n{}", a.display(), b.display())
+    );
+    assert!(!server
+        .received()
+        .iter()
+        .any(|cmd| cmd.path.ends_with("/se/file")));
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn checks_element_and_paths() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    let c = common::connect_mock(&server).await;
+    let (a, b) = scratch_files("upload-checks");
+
+    let text = c.find(Locator::Id("text")).await.unwrap();
+    let err = text.upload_files(&[&a]).await.unwrap_err();
+    assert!(matches!(err, CmdError::InvalidArgument(ref arg, _) if arg == "element"));
+    let one = c.find(Locator::Id("one")).await.unwrap();
+    let err = one.upload_files(&[&a, &b]).await.unwrap_err();
+    assert!(matches!(err, CmdError::InvalidArgument(ref arg, _) if arg == "paths"));
+    let err = one
+        .upload_files(&[a.with_file_name("missing.txt")])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CmdError::InvalidArgument(ref arg, _) if arg == "paths"));
+    let none: &[PathBuf] = &[];
+    let err = one.upload_files(none).await.unwrap_err();
+    assert!(matches!(err, CmdError::InvalidArgument(ref arg, _) if arg == "paths"));
+    assert_eq!(one.attr("value").await.unwrap(), None);
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn selenium_upload() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    server.set_capability("se:vncEnabled", json!(false));
+    server.respond(
+        Method::POST,
+        "se/file",
+        MockResponse::ok(json!("/tmp/upload42/a.txt")),
+    );
+    let c = common::connect_mock(&server).await;
+    let (a, _) = scratch_files("upload-selenium");
+
+    let one = c.find(Locator::Id("one")).await.unwrap();
+    one.upload_files(&[&a]).await.unwrap();
+    assert_eq!(
+        one.attr("value").await.unwrap().as_deref(),
+        Some("/tmp/upload42/a.txt")
+    );
+
+    let upload = server
+        .received()
+        .into_iter()
+        .find(|cmd| cmd.path.ends_with("/se/file"))
+        .unwrap();
+    let file = upload.body.unwrap()["file"].as_str().unwrap().to_string();
+    let zip = base64::engine::general_purpose::STANDARD
+        .decode(file)
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(Cursor::new(zip)).unwrap();
+    assert_eq!(archive.len(), 1);
+    let mut entry = archive.by_index(0).unwrap();
+    assert_eq!(entry.name(), "a.txt");
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "first");
+
+    c.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn falls_back_without_selenium_endpoint() {
+    let server = MockWebDriver::start().await.unwrap();
+    server.set_dom(sample_dom());
+    server.set_capability("se:vncEnabled", json!(false));
+    let c = common::connect_mock(&server).await;
+    let (a, _) = scratch_files("upload-fallback");
+
+    // the mock does not know the endpoint, so the local path is used
+    let one = c.find(Locator::Id("one")).await.unwrap();
+    one.upload_files(&[&a]).await.unwrap();
+    assert_eq!(
+        one.attr("value").await.unwrap(),
+        Some(a.display().to_string())
+    );
+
+    c.close().await.unwrap();
+}